@@ -1,10 +1,10 @@
 pub mod handle_todo {
 
-    use chrono::{self, Local};
-    use core::panic;
+    use chrono::{self, Local, NaiveDate, NaiveDateTime, NaiveTime};
     use std::{
-        fs::{self, write, OpenOptions},
-        io::{Read, Write},
+        env,
+        fs::{self, OpenOptions},
+        io::{self, IsTerminal, Read, Write},
         path::{Path, PathBuf},
         process::exit,
     };
@@ -17,56 +17,177 @@ pub mod handle_todo {
     use clap::ArgMatches;
 
     use crate::{
-        filehandler::file_handler::{self, append_line},
+        filehandler::file_handler::{add_to_gitignore, write_lines, LOCK_FILE_NAME},
         get_current_working_dir,
     };
 
-    use crate::databasehandler::database_handler;
+    use crate::databasehandler::database_handler::todo_database::{
+        database_apply_changes, database_change_todo_Field, database_delete_todos,
+        database_finish_todos, database_get_all_todos, database_get_todos, database_inserte_todo,
+        database_undelete_todo, database_unfinish_todo, tags_in, to_tag, DatabaseField,
+        DeletionMethod, Priority, Todo, TodoState,
+    };
+    use crate::exporthandler::export_handler::{
+        export_todos, format_todos, import_todos, ExchangeFormat, OutputFormat,
+    };
+    use crate::filterhandler::filter_handler::{due_point, parse_filter, sort_todos, SortKey};
+    use crate::githandler::git_handler::{
+        current_branch, head_commit, in_work_tree, last_commit_message,
+    };
+    use crate::hookhandler::hook_handler::{closed_todo_ids, hook_problems, install_hooks};
+    use crate::journalhandler::journal_handler::{
+        journal_record, journal_redo, journal_undo, Change, JOURNAL_FILE_NAME, REDO_FILE_NAME,
+    };
+    use crate::markdownhandler::markdown_handler::sync_markdown;
+    use crate::mergehandler::merge_handler::{merge_files, setup_merge_driver};
+    use crate::scanhandler::scan_handler::{relative_path, scan_comments, sync_comments};
+    use crate::storagehandler::storage_handler::{
+        configured_storage_kind, convert_storage, get_storage, open_storage, StorageKind,
+    };
 
     pub fn handle_delete(delete_args: &ArgMatches, todo_dir: PathBuf) {
-        if let Some(index_s) = delete_args.get_one::<String>("index") {
-            let index = index_s
-                .parse::<usize>()
-                .expect("couldnt parse string to usize");
-
-            //read file
-            let mut todo_file_path = todo_dir.clone();
-            todo_file_path.push(".todo.todo");
-            let mut todo_file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(todo_file_path.clone())
-                .expect("Couldn't open file");
-
-            if is_delete_in_file(todo_dir.clone()) {
-                let mut delete_file_path = todo_dir.clone();
-                delete_file_path.push(".todo.deleted");
-                // let mut finished_file = OpenOptions::new()
-                //     .append(true)
-                //     .open(finished_file_path)
-                //     .expect("Couldn't open file");
-                //get line of index
-                let mut file_content = String::new();
-                todo_file
-                    .read_to_string(&mut file_content)
-                    .expect("failed to read file content");
-                let l: Vec<&str> = file_content.lines().collect();
-                let line: &str = l.get(index).expect("index doesnt exists");
-
-                append_line(&delete_file_path, line.to_string())
-                    .expect("failed to append line to file");
-            }
-            //delete line
-            file_handler::remove_line(&todo_file_path, index).unwrap();
-
-            //paste line in delete_file
-
+        let selectors = get_selectors(delete_args);
+        if !selectors.is_empty() {
+            let todos = get_todos(&todo_dir, TodoState::Open, &selectors);
+            let del_method = if is_delete_in_file(todo_dir.clone()) {
+                DeletionMethod::Mark
+            } else {
+                DeletionMethod::Delete
+            };
+            let deleted = database_delete_todos(
+                &todo_dir,
+                todos.clone(),
+                del_method,
+                get_name(todo_dir.clone()),
+            )
+            .expect("failed to delete todos");
+            record_changes(&todo_dir, "delete", todos, deleted);
             return;
         }
 
         display_todo(true, todo_dir);
     }
 
+    /// The selectors given as `index` arguments. `-` is replaced by the selectors read from stdin.
+    fn get_selectors(args: &ArgMatches) -> Vec<String> {
+        let given = args.get_many::<String>("index").into_iter().flatten();
+        match expand_selectors(given, io::stdin()) {
+            Ok(selectors) => selectors,
+            Err(e) => {
+                println!("{}", e);
+                exit(0);
+            }
+        }
+    }
+
+    /// Replaces `-` in `given` by the whitespace separated selectors in `input`.
+    /// `input` can only be read once, so a second `-` is rejected.
+    fn expand_selectors<'a>(
+        given: impl Iterator<Item = &'a String>,
+        mut input: impl Read,
+    ) -> Result<Vec<String>, String> {
+        let mut selectors = Vec::new();
+        let mut input_read = false;
+        for selector in given {
+            if selector != "-" {
+                selectors.push(selector.clone());
+                continue;
+            }
+            if input_read {
+                return Err("- can only be given once".to_string());
+            }
+            input_read = true;
+            let mut text = String::new();
+            input
+                .read_to_string(&mut text)
+                .map_err(|e| format!("failed to read selectors from stdin: {}", e))?;
+            selectors.extend(text.split_whitespace().map(|s| s.to_string()));
+        }
+        Ok(selectors)
+    }
+
+    /// Returns the todos with `status` selected by `selectors`, see `select_todos`.
+    fn get_todos(todo_dir: &Path, status: TodoState, selectors: &[String]) -> Vec<Todo> {
+        let todos = database_get_todos(todo_dir, status).expect("failed to read todos");
+        match select_todos(&todos, selectors) {
+            Ok(selected) => selected,
+            Err(e) => {
+                println!("{}", e);
+                exit(0);
+            }
+        }
+    }
+
+    /// Every selector is an id, an index or a range of indices like `5-8`.
+    /// Indices refer to `todos` as given, so they don't shift while the selection is applied.
+    /// Every todo is returned once, in the order of `todos`.
+    pub fn select_todos(todos: &[Todo], selectors: &[String]) -> Result<Vec<Todo>, String> {
+        let mut selected = vec![false; todos.len()];
+        for selector in selectors {
+            if let Some(position) = todos.iter().position(|todo| &todo.id == selector) {
+                selected[position] = true;
+                continue;
+            }
+            let (first, last) = match selector.split_once('-') {
+                Some((first, last)) => (first.parse::<usize>(), last.parse::<usize>()),
+                None => (selector.parse::<usize>(), selector.parse::<usize>()),
+            };
+            match (first, last) {
+                (Ok(first), Ok(last)) if first <= last && last < todos.len() => {
+                    selected[first..=last].iter_mut().for_each(|s| *s = true);
+                }
+                _ => return Err(format!("no todo with id or index {}", selector)),
+            }
+        }
+        Ok(todos
+            .iter()
+            .zip(selected)
+            .filter(|(_, selected)| *selected)
+            .map(|(todo, _)| todo.clone())
+            .collect())
+    }
+
+    /// Returns the todo with `status` selected by `selector`, which is either its id
+    /// or its index as shown by `display_todo`.
+    fn get_todo(todo_dir: &Path, status: TodoState, selector: &str) -> Todo {
+        let todos = database_get_todos(todo_dir, status).expect("failed to read todos");
+        match select_todo(todos, selector) {
+            Some(todo) => todo,
+            None => {
+                println!("no todo with id or index {}", selector);
+                exit(0);
+            }
+        }
+    }
+
+    /// Ids are checked first. Ids always contain a letter, so they never shadow an index.
+    pub fn select_todo(todos: Vec<Todo>, selector: &str) -> Option<Todo> {
+        if let Some(position) = todos.iter().position(|todo| todo.id == selector) {
+            return todos.into_iter().nth(position);
+        }
+        let index = selector.parse::<usize>().ok()?;
+        todos.into_iter().nth(index)
+    }
+
+    pub fn handle_restore(restore_args: &ArgMatches, todo_dir: PathBuf) {
+        if let Some(index_s) = restore_args.get_one::<String>("index") {
+            let todo = get_todo(&todo_dir, TodoState::Deleted, index_s);
+            let restored =
+                database_undelete_todo(&todo_dir, todo.clone(), get_name(todo_dir.clone()))
+                    .expect("failed to restore todo");
+            record(&todo_dir, "restore", Some(todo), Some(restored));
+            return;
+        }
+
+        let deleted =
+            database_get_todos(&todo_dir, TodoState::Deleted).expect("failed to read todos");
+        if deleted.is_empty() {
+            println!("no deleted todos in .todo.deleted");
+            return;
+        }
+        display_todos(true, &deleted);
+    }
+
     // Again just GPT4 because why not
     pub fn is_delete_in_file(todo_dir: PathBuf) -> bool {
         let mut config_file_path = todo_dir;
@@ -88,51 +209,164 @@ pub mod handle_todo {
         contents
             .lines()
             .nth(1) // nth(1) for the second line, as nth is zero-indexed
-            .map_or(false, |line| line.trim() == "in_file")
+            .is_some_and(|line| line.trim() == "in_file")
     }
 
-    pub fn handle_finish(finish_args: &ArgMatches, todo_dir: PathBuf) {
-        if let Some(index_s) = finish_args.get_one::<String>("index") {
-            let index = index_s
-                .parse::<usize>()
-                .expect("couldnt parse string to usize");
-
-            //read file
-            let mut todo_file_path = todo_dir.clone();
-            todo_file_path.push(".todo.todo");
-            let mut todo_file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(todo_file_path.clone())
-                .expect("Couldn't open file");
-
-            let mut finished_file_path = todo_dir.clone();
-            finished_file_path.push(".todo.finished");
-            // let mut finished_file = OpenOptions::new()
-            //     .append(true)
-            //     .open(finished_file_path)
-            //     .expect("Couldn't open file");
-
-            //get line of index
-            let mut file_content = String::new();
-            todo_file
-                .read_to_string(&mut file_content)
-                .expect("failed to read file content");
-            let l: Vec<&str> = file_content.lines().collect();
-            let line: &str = l.get(index).expect("index doesnt exists");
-            //delete line
-            file_handler::remove_line(&todo_file_path, index).unwrap();
-
-            //paste line in delete_file
-            append_line(&finished_file_path, line.to_string())
-                .expect("failed to append line to file");
+    pub fn handle_reopen(reopen_args: &ArgMatches, todo_dir: PathBuf) {
+        if let Some(index_s) = reopen_args.get_one::<String>("index") {
+            let todo = get_todo(&todo_dir, TodoState::Done, index_s);
+            let reopened =
+                database_unfinish_todo(&todo_dir, todo.clone(), get_name(todo_dir.clone()))
+                    .expect("failed to reopen todo");
+            record(&todo_dir, "reopen", Some(todo), Some(reopened));
+            return;
+        }
+
+        let finished =
+            database_get_todos(&todo_dir, TodoState::Done).expect("failed to read todos");
+        if finished.is_empty() {
+            println!("no finished todos in .todo.finished");
+            return;
+        }
+        display_todos(true, &finished);
+    }
 
+    pub fn handle_finish(finish_args: &ArgMatches, todo_dir: PathBuf) {
+        let selectors = get_selectors(finish_args);
+        if !selectors.is_empty() {
+            let todos = get_todos(&todo_dir, TodoState::Open, &selectors);
+            finish_todos(&todo_dir, todos);
             return;
         }
 
         display_todo(true, todo_dir);
     }
 
+    fn finish_todos(todo_dir: &Path, todos: Vec<Todo>) {
+        let finished = database_finish_todos(todo_dir, todos.clone(), get_name(todo_dir.into()))
+            .expect("failed to finish todos");
+        record_changes(
+            todo_dir,
+            "finish",
+            todos,
+            finished.into_iter().map(Some).collect(),
+        );
+    }
+
+    pub fn handle_hooks(hooks_args: &ArgMatches, todo_dir: PathBuf) {
+        match hooks_args.subcommand() {
+            Some(("install", install_args)) => {
+                let exe = env::current_exe().expect("Could not find the todo executable!");
+                let summary = match install_hooks(&todo_dir, &exe, install_args.get_flag("block")) {
+                    Ok(summary) => summary,
+                    Err(e) => {
+                        println!("{}", e);
+                        exit(0);
+                    }
+                };
+                if !summary.installed.is_empty() {
+                    println!(
+                        "installed {} in {}",
+                        summary.installed.join(", "),
+                        summary.hooks_dir.to_string_lossy()
+                    );
+                }
+                for hook in summary.skipped {
+                    println!(
+                        "{} already exists, add `todo hooks run {}` to it yourself",
+                        hook, hook
+                    );
+                }
+            }
+            Some(("run", run_args)) => {
+                let hook = run_args
+                    .get_one::<String>("hook")
+                    .expect("hook is required");
+                if hook == "post-commit" {
+                    close_committed_todos(&todo_dir);
+                    return;
+                }
+                let todos =
+                    database_get_todos(&todo_dir, TodoState::Open).expect("failed to read todos");
+                let problems = hook_problems(&todos, Local::now().naive_local());
+                if problems.is_empty() {
+                    return;
+                }
+                println!("todo: {} overdue or blocking todos", problems.len());
+                for (todo, reason) in &problems {
+                    println!("{}\t{}\t({})", todo.id, todo.description, reason);
+                }
+                if run_args.get_flag("block") {
+                    println!("{} stopped, use --no-verify to skip this check", hook);
+                    exit(1);
+                }
+            }
+            _ => println!("Please try `todo hooks help` to see how to use this."),
+        }
+    }
+
+    /// Run by git as `todo merge-driver %O %A %B`. Exits with 1 if the files couldn't be
+    /// merged, so git reports a conflict.
+    pub fn handle_merge_driver(merge_args: &ArgMatches) {
+        let file = |name: &str| {
+            PathBuf::from(
+                merge_args
+                    .get_one::<String>(name)
+                    .expect("clap requires all three files"),
+            )
+        };
+        match merge_files(&file("base"), &file("ours"), &file("theirs")) {
+            Ok(result) => {
+                for id in result.conflicts {
+                    println!(
+                        "todo: {} was changed on both sides, kept the newer change",
+                        id
+                    );
+                }
+            }
+            Err(e) => {
+                println!("todo: couldn't merge: {}", e);
+                exit(1);
+            }
+        }
+    }
+
+    pub fn handle_merge_setup(todo_dir: PathBuf) {
+        let exe = env::current_exe().expect("Could not find the todo executable!");
+        match setup_merge_driver(&todo_dir, &exe) {
+            Ok(added) => {
+                println!("configured the todo merge driver in git");
+                for line in added {
+                    println!("added `{}` to .gitattributes", line);
+                }
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    /// Finishes the todos named in the `Closes-Todo:` trailers of the last commit.
+    fn close_committed_todos(todo_dir: &Path) {
+        let message = last_commit_message(todo_dir).unwrap_or_default();
+        let ids = closed_todo_ids(&message);
+        if ids.is_empty() {
+            return;
+        }
+        let open = database_get_todos(todo_dir, TodoState::Open).expect("failed to read todos");
+        let mut todos = Vec::new();
+        for id in ids {
+            match open.iter().find(|todo| todo.id == id) {
+                Some(todo) => {
+                    println!("todo: finished {}\t{}", todo.id, todo.description);
+                    todos.push(todo.clone());
+                }
+                None => println!("todo: {} is not an open todo", id),
+            }
+        }
+        if !todos.is_empty() {
+            finish_todos(todo_dir, todos);
+        }
+    }
+
     pub fn handle_config(config_args: &ArgMatches) {
         let current_dir = get_current_working_dir().unwrap();
         let mut config_path = current_dir.clone();
@@ -145,62 +379,20 @@ pub mod handle_todo {
 
         if let Some(name_args) = config_args.subcommand_matches("name") {
             let name_value = name_args.get_one::<String>("new_name").unwrap();
-
-            let mut content = String::new();
-            if let Ok(mut file) = OpenOptions::new().read(true).open(config_path.clone()) {
-                file.read_to_string(&mut content).unwrap();
-            }
-
-            let mut content_lines: Vec<&str> = content.lines().collect();
-            if content_lines.len() != 2 {
-                println!("config file is broken!");
-                exit(0);
-            }
-            content_lines.remove(0);
-            content_lines.insert(0, name_value);
-            // println!("{:?}", content_lines);
-
-            write(
-                config_path,
-                format!(
-                    "{}\n{}",
-                    content_lines.first().unwrap(),
-                    content_lines.get(1).unwrap()
-                )
-                .as_bytes(),
-            )
-            .unwrap();
+            set_config_line(&config_path, 0, name_value);
             return;
         }
 
         if let Some(deleted_args) = config_args.subcommand_matches("deleted") {
             let deleted_value = deleted_args.get_one::<String>("delete_method").unwrap();
-
-            let mut content = String::new();
-            if let Ok(mut file) = OpenOptions::new().read(true).open(config_path.clone()) {
-                file.read_to_string(&mut content).unwrap();
-            }
-
-            let mut content_lines: Vec<&str> = content.lines().collect();
-            if content_lines.len() != 2 {
-                println!("config file is broken!");
-                exit(0);
-            }
-
-            content_lines.remove(1);
             let deleted_parsed_value = parse_deleted_value(deleted_value);
-            content_lines.insert(1, &deleted_parsed_value);
-
-            write(
-                config_path,
-                format!(
-                    "{}\n{}",
-                    content_lines.first().unwrap(),
-                    content_lines.get(1).unwrap()
-                )
-                .as_bytes(),
-            )
-            .unwrap();
+            set_config_line(&config_path, 1, &deleted_parsed_value);
+            return;
+        }
+
+        if let Some(storage_args) = config_args.subcommand_matches("storage") {
+            let storage_value = storage_args.get_one::<String>("storage").unwrap();
+            migrate_storage(&current_dir, storage_value);
             return;
         }
 
@@ -216,6 +408,188 @@ pub mod handle_todo {
         return;
     }
 
+    pub fn handle_import(import_args: &ArgMatches, todo_dir: PathBuf) {
+        let format = import_args.get_one::<String>("from").unwrap();
+        let format = ExchangeFormat::from_str(format).expect("clap only allows known formats");
+        let file = import_args.get_one::<String>("file").unwrap();
+        let content = match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(e) => {
+                println!("couldn't read {}: {}", file, e);
+                exit(0);
+            }
+        };
+        let todos = match import_todos(&content, &format, &get_name(todo_dir.clone())) {
+            Ok(todos) => todos,
+            Err(e) => {
+                println!("couldn't import {}: {}", file, e);
+                exit(0);
+            }
+        };
+
+        let known = database_get_all_todos(&todo_dir).expect("failed to read todos");
+        let count = todos.len();
+        // already imported or exported from here
+        let changes: Vec<(Option<Todo>, Option<Todo>)> = todos
+            .into_iter()
+            .filter(|todo| !known.iter().any(|stored| stored.id == todo.id))
+            .map(|todo| (None, Some(todo)))
+            .collect();
+        let skipped = count - changes.len();
+        let added = database_apply_changes(&todo_dir, changes).expect("failed to add todos");
+        println!(
+            "imported {} todos, skipped {} known ones",
+            added.len(),
+            skipped
+        );
+        record_added(&todo_dir, "import", added);
+    }
+
+    pub fn handle_export(export_args: &ArgMatches, todo_dir: PathBuf) {
+        let format = export_args.get_one::<String>("to").unwrap();
+        let format = ExchangeFormat::from_str(format).expect("clap only allows known formats");
+        let todos = database_get_all_todos(&todo_dir).expect("failed to read todos");
+        print!("{}", export_todos(&todos, &format));
+    }
+
+    pub fn handle_sync_md(sync_args: &ArgMatches, todo_dir: PathBuf) {
+        let file = PathBuf::from(sync_args.get_one::<String>("file").unwrap());
+        let summary = match sync_markdown(&todo_dir, &file, &get_name(todo_dir.clone())) {
+            Ok(summary) => summary,
+            Err(e) => {
+                println!("couldn't sync {}: {}", file.to_string_lossy(), e);
+                exit(0);
+            }
+        };
+        for id in &summary.unknown_ids {
+            println!("no open or finished todo with id {}, left as it is", id);
+        }
+        println!(
+            "{} todos changed, {} added to {}",
+            summary.changes.len(),
+            summary.added_to_file,
+            file.to_string_lossy()
+        );
+        if let Err(e) = journal_record(&todo_dir, "sync-md", summary.changes) {
+            println!("couldn't write the journal, this can't be undone: {}", e);
+        }
+    }
+
+    pub fn handle_scan(scan_args: &ArgMatches, todo_dir: PathBuf) {
+        let root = fs::canonicalize(&todo_dir).expect("todo directory exists");
+        let start = match scan_args.get_one::<String>("path") {
+            Some(path) => match fs::canonicalize(path) {
+                Ok(start) if start.starts_with(&root) => start,
+                Ok(_) => {
+                    println!("{} is not below {}", path, root.to_string_lossy());
+                    exit(0);
+                }
+                Err(e) => {
+                    println!("couldn't find {}: {}", path, e);
+                    exit(0);
+                }
+            },
+            None => root.clone(),
+        };
+
+        let comments = scan_comments(&root, &start).expect("failed to scan");
+        let finish_gone = scan_args.get_flag("finish");
+        let summary = sync_comments(
+            &todo_dir,
+            comments,
+            &relative_path(&root, &start),
+            &get_name(todo_dir.clone()),
+            finish_gone,
+        )
+        .expect("failed to add todos");
+
+        println!("{} new todos from comments", summary.added);
+        for todo in &summary.gone {
+            let source = todo.source.clone().unwrap_or_default();
+            if finish_gone {
+                println!(
+                    "finished {}\t{}\t({} is gone)",
+                    todo.id, todo.description, source
+                );
+            } else {
+                println!("{}\t{}\t({} is gone)", todo.id, todo.description, source);
+            }
+        }
+        if !summary.gone.is_empty() && !finish_gone {
+            println!("use `todo scan --finish` to finish todos whose comment is gone");
+        }
+        if let Err(e) = journal_record(&todo_dir, "scan", summary.changes) {
+            println!("couldn't write the journal, this can't be undone: {}", e);
+        }
+    }
+
+    pub fn handle_migrate(migrate_args: &ArgMatches, todo_dir: PathBuf) {
+        let storage_value = migrate_args.get_one::<String>("to").unwrap();
+        migrate_storage(&todo_dir, storage_value);
+    }
+
+    /// Moves all todos into the storage named `storage_value` and selects it in the config.
+    fn migrate_storage(todo_dir: &Path, storage_value: &str) {
+        let new_kind = match StorageKind::from_str(storage_value) {
+            Some(kind) => kind,
+            None => {
+                println!(
+                    "unknown storage: {} (use tab, jsonl or sqlite)",
+                    storage_value
+                );
+                exit(0);
+            }
+        };
+        let old_kind = configured_storage_kind(todo_dir);
+        if old_kind == new_kind {
+            println!("todos are already stored as {}", new_kind.as_str());
+            return;
+        }
+
+        let moved = match convert_storage(todo_dir, &old_kind, &new_kind) {
+            Ok(moved) => moved,
+            Err(e) => {
+                println!(
+                    "migration failed: {}. The todos are still stored as {}",
+                    e,
+                    old_kind.as_str()
+                );
+                exit(1);
+            }
+        };
+
+        let mut config_path = todo_dir.to_path_buf();
+        config_path.push(".todo.config");
+        set_config_line(&config_path, 2, new_kind.as_str());
+
+        // line storages share their files, so only files the new storage doesn't use are removed
+        let new_files = open_storage(todo_dir, &new_kind).files();
+        for file in open_storage(todo_dir, &old_kind).files() {
+            if !new_files.contains(&file) && file.exists() {
+                fs::remove_file(&file).expect("failed to remove old todo file");
+            }
+        }
+        println!("moved {} todos to {}", moved, new_kind.as_str());
+    }
+
+    /// Replaces line `index` of `.todo.config`. Missing lines are filled with their default.
+    fn set_config_line(config_path: &Path, index: usize, value: &str) {
+        let content = fs::read_to_string(config_path).unwrap();
+        let mut content_lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        if content_lines.len() < 2 {
+            println!("config file is broken!");
+            exit(0);
+        }
+
+        let defaults = ["default-name", "in_file", StorageKind::Tab.as_str()];
+        while content_lines.len() <= index {
+            content_lines.push(defaults[content_lines.len()].to_string());
+        }
+        content_lines[index] = value.to_string();
+
+        write_lines(&config_path.to_path_buf(), content_lines).unwrap();
+    }
+
     pub fn parse_deleted_value(deleted_value: &str) -> String {
         let in_file_distance = edit_distance(deleted_value, "in_file");
         let delete_distance = edit_distance(deleted_value, "delete");
@@ -240,6 +614,7 @@ pub mod handle_todo {
                 let mut config_str = String::new();
                 config_str.push_str("default-name\n");
                 config_str.push_str("in_file\n");
+                config_str.push_str("tab\n");
                 config_str
             }
         };
@@ -251,6 +626,7 @@ pub mod handle_todo {
         } else {
             let mut file = OpenOptions::new()
                 .create(true)
+                .truncate(true)
                 .write(true)
                 .open(config_path)
                 .unwrap();
@@ -258,73 +634,201 @@ pub mod handle_todo {
             println!(".todo.config created");
         }
 
-        let mut todo_path = current_dir.clone();
-        todo_path.push(".todo.todo");
-        if !todo_path.exists() {
-            println!("{}", todo_path.clone().to_string_lossy());
-            fs::write(todo_path, "").unwrap();
-            println!(".todo.todo created");
+        let created = get_storage(&current_dir)
+            .create()
+            .expect("failed to create todo files");
+        for file_name in created {
+            println!("{} created", file_name);
         }
 
-        let mut finished_path = current_dir.clone();
-        finished_path.push(".todo.finished");
-        if !finished_path.exists() {
-            println!("{}", finished_path.clone().to_string_lossy());
-            fs::write(finished_path, "").unwrap();
-            println!(".todo.finished created");
+        // the lock and the journal only make sense on this machine
+        if in_work_tree(&current_dir) {
+            let local_files = [LOCK_FILE_NAME, JOURNAL_FILE_NAME, REDO_FILE_NAME];
+            match add_to_gitignore(&current_dir, &local_files) {
+                Ok(added) if !added.is_empty() => {
+                    println!("added {} to .gitignore", added.join(", "))
+                }
+                Ok(_) => {}
+                Err(e) => println!("couldn't update .gitignore: {}", e),
+            }
         }
+    }
 
-        let mut deleted_path = current_dir.clone();
-        deleted_path.push(".todo.deleted");
-        if !deleted_path.exists() {
-            println!("{}", deleted_path.clone().to_string_lossy());
-            fs::write(deleted_path, "").unwrap();
-            println!(".todo.deleted created");
+    pub fn handle_list(list_args: &ArgMatches, todo_dir: PathBuf) {
+        let matching = match list_todos(list_args, &todo_dir) {
+            Ok(matching) => matching,
+            Err(e) => {
+                println!("{}", e);
+                exit(0);
+            }
+        };
+
+        match list_args.get_one::<String>("format") {
+            Some(format) => {
+                let format =
+                    OutputFormat::from_str(format).expect("clap only allows known formats");
+                print!("{}", format_todos(&matching, &format));
+            }
+            None => display_todos(false, &matching),
         }
     }
 
-    pub fn display_todo(with_index: bool, todo_dir: PathBuf) {
-        let mut todo_file_path = todo_dir.clone();
-        todo_file_path.push(".todo.todo");
-        let mut file = match OpenOptions::new()
-            .read(true)
-            .write(false)
-            .append(false)
-            .create(false)
-            .open(&todo_file_path)
-        {
-            Ok(file) => file,
-            Err(e) => panic!("failed to open file! {}", e),
+    /// The todos `todo list` shows, filtered and sorted. `--branch` without name is the
+    /// branch checked out in the repository around `todo_dir`.
+    fn list_todos(list_args: &ArgMatches, todo_dir: &Path) -> Result<Vec<Todo>, String> {
+        let filter_words: Vec<&str> = list_args
+            .get_many::<String>("filter")
+            .into_iter()
+            .flatten()
+            .map(|word| word.as_str())
+            .collect();
+        let filter = if filter_words.is_empty() {
+            None
+        } else {
+            Some(parse_filter(&filter_words.join(" "))?)
         };
 
-        let mut string_file = String::new();
-        file.read_to_string(&mut string_file).unwrap();
-        let mut index: usize = 0;
-        for line in string_file.lines() {
+        let todos = if filter
+            .as_ref()
+            .is_some_and(|filter| filter.mentions_status())
+        {
+            database_get_all_todos(todo_dir)
+        } else {
+            database_get_todos(todo_dir, TodoState::Open)
+        }
+        .expect("failed to read todos");
+        let mut matching: Vec<Todo> = todos
+            .into_iter()
+            .filter(|todo| filter.as_ref().is_none_or(|filter| filter.matches(todo)))
+            .collect();
+        if let Some(branch) = list_args.get_one::<String>("branch") {
+            let branch = match branch.as_str() {
+                "HEAD" => {
+                    current_branch(todo_dir).ok_or("not on a git branch, use `--branch {name}`")?
+                }
+                name => name.to_string(),
+            };
+            matching.retain(|todo| todo.git_branch.as_ref() == Some(&branch));
+        }
+
+        if let Some(sort) = list_args.get_one::<String>("sort") {
+            let sort_key = SortKey::from_str(sort).expect("clap only allows known sort keys");
+            let descending = list_args
+                .get_one::<String>("order")
+                .is_some_and(|order| order == "desc");
+            sort_todos(&mut matching, &sort_key, descending);
+        }
+        Ok(matching)
+    }
+
+    /// Lists the tags of the open todos, the most used first.
+    pub fn handle_tags(todo_dir: PathBuf) {
+        let todos = database_get_todos(&todo_dir, TodoState::Open).expect("failed to read todos");
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for tag in todos.iter().flat_map(|todo| &todo.tags) {
+            match counts.iter_mut().find(|(known, _)| known == tag) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((tag.clone(), 1)),
+            }
+        }
+        if counts.is_empty() {
+            println!("no tags, add them like `todo add \"fix login +backend\"` or with --tag");
+            return;
+        }
+        counts.sort_by(|(a_tag, a_count), (b_tag, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_tag.cmp(b_tag))
+        });
+        for (tag, count) in counts {
+            println!("{}\t{}", tag, count);
+        }
+    }
+
+    pub fn display_todo(with_index: bool, todo_dir: PathBuf) {
+        let todos = database_get_todos(&todo_dir, TodoState::Open).expect("failed to read todos");
+        display_todos(with_index, &todos);
+    }
+
+    fn display_todos(with_index: bool, todos: &[Todo]) {
+        let now = Local::now().naive_local();
+        let colored = use_color();
+        for (index, todo) in todos.iter().enumerate() {
             let mut display_line = String::new();
             if with_index {
                 display_line.push_str(&format!("[{}]\t", index));
-                index += 1;
             }
-            let values: Vec<&str> = line.split('\t').collect();
+            display_line.push_str(&format!("{}\t", todo.id));
+            let due_date = todo
+                .due_date
+                .map(|d| d.format("%d.%m.%Y").to_string())
+                .unwrap_or_default();
+            if let Some(priority) = todo.priority {
+                display_line.push_str(&format!("({}) ", priority.as_str()));
+            }
+            display_line.push_str(&todo.description);
+            // tags given with --tag
+            let described = tags_in(&todo.description);
+            for tag in todo.tags.iter().filter(|tag| !described.contains(tag)) {
+                display_line.push_str(&format!(" {}", tag));
+            }
+            display_line.push_str(&format!("\t{}\t", due_date));
+            if todo.status != TodoState::Open {
+                println!("{}", display_line);
+                continue;
+            }
+            display_line.push_str(&relative_due(todo, now).unwrap_or_default());
 
-            // get_name(),
-            // description,
-            // due_date,
-            // due_time,
-            // date,
-            // time
-            let name = values.get(0).unwrap_or(&"");
-            let description = values.get(1).unwrap_or(&"");
-            let due_date = values.get(2).unwrap_or(&"");
-            let due_time = values.get(3).unwrap_or(&"");
-            let date = values.get(4).unwrap_or(&"");
-            let time = values.get(5).unwrap_or(&"");
-            display_line.push_str(&format!("{}\t", description));
-            display_line.push_str(&format!("{}\t", due_date));
-            println!("{}", display_line);
+            match due_color(todo, now) {
+                Some(color) if colored => println!("{}{}{}", color, display_line, RESET_COLOR),
+                _ => println!("{}", display_line),
+            }
+        }
+    }
+
+    const RED: &str = "\x1b[31m";
+    const YELLOW: &str = "\x1b[33m";
+    const RESET_COLOR: &str = "\x1b[0m";
+
+    /// Colors are only used in a terminal and never if `NO_COLOR` is set (https://no-color.org).
+    fn use_color() -> bool {
+        env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal()
+    }
+
+    /// Red if overdue, yellow if due today.
+    fn due_color(todo: &Todo, now: NaiveDateTime) -> Option<&'static str> {
+        let due = due_point(todo)?;
+        if due < now {
+            Some(RED)
+        } else if due.date() == now.date() {
+            Some(YELLOW)
+        } else {
+            None
+        }
+    }
+
+    /// e.g. "in 3h", "2d overdue" or "today". Hours and minutes are only shown
+    /// if the todo has a due time.
+    pub fn relative_due(todo: &Todo, now: NaiveDateTime) -> Option<String> {
+        let due = due_point(todo)?;
+        if todo.due_time.is_none() {
+            let days = (due.date() - now.date()).num_days();
+            return Some(match days {
+                0 => "today".to_string(),
+                days if days < 0 => format!("{}d overdue", -days),
+                days => format!("in {}d", days),
+            });
+        }
+
+        let minutes = (due - now).num_minutes();
+        let span = match minutes.abs() {
+            m if m < 60 => format!("{}m", m),
+            m if m < 60 * 24 => format!("{}h", m / 60),
+            m => format!("{}d", m / (60 * 24)),
+        };
+        if minutes < 0 {
+            Some(format!("{} overdue", span))
+        } else {
+            Some(format!("in {}", span))
         }
-        // println!("{}", string_file);
     }
 
     pub fn handle_add(add_args: &ArgMatches, todo_dir: PathBuf) {
@@ -332,30 +836,166 @@ pub mod handle_todo {
             None => todo!("no description given!"),
             Some(s) => s.clone(),
         };
-        let due_date = match add_args.get_one::<String>("due_date") {
-            None => todo!("no due date given!"),
-            Some(s) => parse_due_date(s.clone()),
-        };
-        let due_time = match add_args.get_one::<String>("due_time") {
-            None => todo!("no due time given!"),
-            Some(s) => parse_due_time(s.clone()),
-        };
-        let now = Local::now();
-        let date = now.format("%H:%M").to_string();
-        let time = now.format("%y-%m-%d").to_string();
-        let todo_to_add: String = format!(
-            "{}\t{}\t{}\t{}\t{}\t{}",
-            get_name(todo_dir.clone()),
+        let due_date = add_args
+            .get_one::<String>("due_date")
+            .map(|s| to_due_date(s.clone()));
+        let due_time = add_args
+            .get_one::<String>("due_time")
+            .map(|s| to_due_time(s.clone()));
+
+        let mut todo = Todo::new(
             description,
+            TodoState::Open,
             due_date,
             due_time,
-            date,
-            time
+            Local::now().naive_local(),
+            get_name(todo_dir.clone()),
         );
+        todo.priority = add_args
+            .get_one::<String>("priority")
+            .map(|p| Priority::from_str(p).expect("clap only allows known priorities"));
+        for tag in add_args.get_many::<String>("tag").into_iter().flatten() {
+            // `--tag backend` is `+backend`
+            let Some(tag) = to_tag(tag) else {
+                continue;
+            };
+            if !todo.tags.contains(&tag) {
+                todo.tags.push(tag);
+            }
+        }
+        // the repository of the todos, wherever in it `todo` runs
+        todo.git_branch = current_branch(&todo_dir);
+        todo.git_commit = head_commit(&todo_dir);
+        let added = database_inserte_todo(&todo_dir, todo).expect("failed to add todo");
+        record(&todo_dir, "add", None, Some(added));
+    }
+
+    /// Changes the description, due date, due time or priority of an open todo in one write.
+    pub fn handle_edit(edit_args: &ArgMatches, todo_dir: PathBuf) {
+        let index_s = edit_args
+            .get_one::<String>("index")
+            .expect("index is required");
+        let nothing_given = ["description", "due_date", "due_time", "priority"]
+            .iter()
+            .all(|arg| edit_args.get_one::<String>(arg).is_none());
+        if nothing_given {
+            println!("nothing to change, use --description, -d, -t or -p");
+            return;
+        }
+        let todo = get_todo(&todo_dir, TodoState::Open, index_s);
+
+        let mut fields = Vec::new();
+        if let Some(description) = edit_args.get_one::<String>("description") {
+            // the tags of the old description go, the ones given with --tag stay
+            let old_tags = tags_in(&todo.description);
+            let mut tags = todo.tags.clone();
+            tags.retain(|tag| !old_tags.contains(tag));
+            for tag in tags_in(description) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            fields.push(DatabaseField::Description(description.clone()));
+            fields.push(DatabaseField::Tags(tags));
+        }
+        if let Some(due_date) = edit_args.get_one::<String>("due_date") {
+            let due_date = (due_date != "none").then(|| to_due_date(due_date.clone()));
+            fields.push(DatabaseField::DueDate(due_date));
+        }
+        if let Some(due_time) = edit_args.get_one::<String>("due_time") {
+            let due_time = (due_time != "none").then(|| to_due_time(due_time.clone()));
+            fields.push(DatabaseField::DueTime(due_time));
+        }
+        if let Some(priority) = edit_args.get_one::<String>("priority") {
+            fields.push(DatabaseField::Priority(Priority::from_str(priority)));
+        }
+
+        let edited =
+            database_change_todo_Field(&todo_dir, todo.clone(), fields, get_name(todo_dir.clone()))
+                .expect("failed to edit todo");
+        record(&todo_dir, "edit", Some(todo), Some(edited));
+    }
+
+    /// Writes a change into the journal, so `todo undo` can reverse it.
+    fn record(todo_dir: &Path, command: &str, before: Option<Todo>, after: Option<Todo>) {
+        if let Err(e) = journal_record(todo_dir, command, vec![Change { before, after }]) {
+            println!("couldn't write the journal, this can't be undone: {}", e);
+        }
+    }
+
+    /// Records one operation that changed every todo in `before` into the same entry of `after`.
+    fn record_changes(todo_dir: &Path, command: &str, before: Vec<Todo>, after: Vec<Option<Todo>>) {
+        let changes = before
+            .into_iter()
+            .zip(after)
+            .map(|(before, after)| Change {
+                before: Some(before),
+                after,
+            })
+            .collect();
+        if let Err(e) = journal_record(todo_dir, command, changes) {
+            println!("couldn't write the journal, this can't be undone: {}", e);
+        }
+    }
+
+    /// Records one operation that added the todos in `added`.
+    fn record_added(todo_dir: &Path, command: &str, added: Vec<Option<Todo>>) {
+        let changes = added
+            .into_iter()
+            .map(|after| Change {
+                before: None,
+                after,
+            })
+            .collect();
+        if let Err(e) = journal_record(todo_dir, command, changes) {
+            println!("couldn't write the journal, this can't be undone: {}", e);
+        }
+    }
 
-        let mut todo_file_path = todo_dir.clone();
-        todo_file_path.push(".todo.todo");
-        append_line(&todo_file_path, todo_to_add).expect("failed to append line to file");
+    pub fn handle_undo(undo_args: &ArgMatches, todo_dir: PathBuf) {
+        let count = *undo_args.get_one::<usize>("count").unwrap();
+        match journal_undo(&todo_dir, count) {
+            Ok(undone) if undone.is_empty() => println!("nothing to undo"),
+            Ok(undone) => undone
+                .iter()
+                .for_each(|operation| println!("undid {}", operation.describe())),
+            Err(e) => println!("couldn't undo: {}", e),
+        }
+    }
+
+    pub fn handle_redo(redo_args: &ArgMatches, todo_dir: PathBuf) {
+        let count = *redo_args.get_one::<usize>("count").unwrap();
+        match journal_redo(&todo_dir, count) {
+            Ok(redone) if redone.is_empty() => println!("nothing to redo"),
+            Ok(redone) => redone
+                .iter()
+                .for_each(|operation| println!("redid {}", operation.describe())),
+            Err(e) => println!("couldn't redo: {}", e),
+        }
+    }
+
+    /// Parses a due date like `-d` does. Exits if it can't be understood.
+    pub fn to_due_date(due_date_string: String) -> NaiveDate {
+        let parsed = parse_due_date(due_date_string.clone());
+        match NaiveDate::parse_from_str(&parsed, "%d.%m.%Y") {
+            Ok(date) => date,
+            Err(_) => {
+                println!("couldn't understand due date: {}", due_date_string);
+                exit(0);
+            }
+        }
+    }
+
+    /// Parses a due time like `-t` does. Exits if it can't be understood.
+    pub fn to_due_time(due_time_string: String) -> NaiveTime {
+        let parsed = parse_due_time(due_time_string.clone());
+        match NaiveTime::parse_from_str(&parsed, "%H:%M") {
+            Ok(time) => time,
+            Err(_) => {
+                println!("couldn't understand due time: {}", due_time_string);
+                exit(0);
+            }
+        }
     }
 
     pub fn get_name(todo_dir: PathBuf) -> String {
@@ -372,10 +1012,263 @@ pub mod handle_todo {
 
         line.to_string()
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::clapargs::clap_args::cli;
+        use crate::testhelper::test_helper::{
+            git, test_todo, test_todo_dir, test_todo_repo, test_todos,
+        };
+
+        #[test]
+        fn test_select_todos_lists_and_ranges() {
+            let todos = test_todos(6);
+            let selectors: Vec<String> = ["4", "1-2", &todos[5].id, "2"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+            let selected = select_todos(&todos, &selectors).unwrap();
+            let expected = vec![
+                todos[1].clone(),
+                todos[2].clone(),
+                todos[4].clone(),
+                todos[5].clone(),
+            ];
+            assert_eq!(selected, expected);
+        }
+
+        #[test]
+        fn test_expand_selectors() {
+            let given = |selectors: &[&str]| -> Vec<String> {
+                selectors.iter().map(|s| s.to_string()).collect()
+            };
+            let expanded =
+                expand_selectors(given(&["1", "-", "4"]).iter(), "abc123\n2 3\n".as_bytes());
+            let twice = expand_selectors(given(&["-", "1", "-"]).iter(), "2\n".as_bytes());
+
+            assert_eq!(expanded, Ok(given(&["1", "abc123", "2", "3", "4"])));
+            assert!(twice.is_err());
+        }
+
+        #[test]
+        fn test_relative_due() {
+            let now = NaiveDate::from_ymd_opt(2024, 5, 10)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            let mut todo = test_todo("todo 0");
+            let cases = [
+                ((2024, 5, 10), None, "today", Some(YELLOW)),
+                ((2024, 5, 8), None, "2d overdue", Some(RED)),
+                ((2024, 5, 13), None, "in 3d", None),
+                ((2024, 5, 10), Some((15, 30)), "in 3h", Some(YELLOW)),
+                ((2024, 5, 10), Some((11, 35)), "25m overdue", Some(RED)),
+                ((2024, 5, 12), Some((13, 0)), "in 2d", None),
+            ];
+            for ((year, month, day), time, hint, color) in cases {
+                todo.due_date = NaiveDate::from_ymd_opt(year, month, day);
+                todo.due_time = time.and_then(|(h, m)| NaiveTime::from_hms_opt(h, m, 0));
+                assert_eq!(relative_due(&todo, now).as_deref(), Some(hint));
+                assert_eq!(due_color(&todo, now), color);
+            }
+            todo.due_date = None;
+            assert_eq!(relative_due(&todo, now), None);
+            assert_eq!(due_color(&todo, now), None);
+        }
+
+        /// The arguments of the subcommand in `command`, e.g. `["restore", "0"]`.
+        fn args(command: &[&str]) -> ArgMatches {
+            let matches = cli().get_matches_from([&["todo"], command].concat());
+            matches.subcommand().unwrap().1.clone()
+        }
+
+        fn add_todos(todo_dir: &Path, count: usize) -> Vec<Todo> {
+            test_todos(count)
+                .into_iter()
+                .map(|todo| database_inserte_todo(todo_dir, todo).unwrap())
+                .collect()
+        }
+
+        #[test]
+        fn test_restore() {
+            let todo_dir = test_todo_dir("brain-restore");
+            let todos = add_todos(&todo_dir, 2);
+            handle_delete(&args(&["delete", &todos[1].id]), todo_dir.clone());
+            let deleted = database_get_todos(&todo_dir, TodoState::Deleted).unwrap();
+
+            handle_restore(&args(&["restore", "0"]), todo_dir.clone());
+            let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            let deleted_after_restore = database_get_todos(&todo_dir, TodoState::Deleted).unwrap();
+            handle_undo(&args(&["undo"]), todo_dir.clone());
+            let deleted_after_undo = database_get_todos(&todo_dir, TodoState::Deleted).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(deleted.len(), 1);
+            assert_eq!(open.len(), 2);
+            assert_eq!(open[1].id, todos[1].id);
+            assert_eq!(open[1].status, TodoState::Open);
+            assert_eq!(open[1].last_changed_by, "carol");
+            assert!(deleted_after_restore.is_empty());
+            assert_eq!(deleted_after_undo, deleted);
+        }
+
+        #[test]
+        fn test_reopen() {
+            let todo_dir = test_todo_dir("brain-reopen");
+            let todos = add_todos(&todo_dir, 3);
+            handle_finish(&args(&["finish", "0", "2"]), todo_dir.clone());
+            let finished = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+
+            handle_reopen(&args(&["reopen", &todos[2].id]), todo_dir.clone());
+            let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            let finished_after_reopen = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+            handle_undo(&args(&["undo"]), todo_dir.clone());
+            let finished_after_undo = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(finished.len(), 2);
+            assert!(finished[1].done_date.is_some());
+            assert_eq!(open.len(), 2);
+            assert_eq!(open[1].id, todos[2].id);
+            assert_eq!(open[1].done_date, None);
+            assert_eq!(open[1].last_changed_by, "carol");
+            assert_eq!(finished_after_reopen, vec![finished[0].clone()]);
+            assert_eq!(finished_after_undo, finished);
+        }
+
+        #[test]
+        fn test_edit() {
+            let todo_dir = test_todo_dir("brain-edit");
+            let todos = add_todos(&todo_dir, 1);
+            handle_edit(
+                &args(&[
+                    "edit",
+                    &todos[0].id,
+                    "--description",
+                    "todo 0 +backend",
+                    "-p",
+                    "high",
+                    "-d",
+                    "1.5.2030",
+                ]),
+                todo_dir.clone(),
+            );
+            let edited = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            handle_undo(&args(&["undo"]), todo_dir.clone());
+            let undone = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(edited[0].description, "todo 0 +backend");
+            assert_eq!(edited[0].tags, vec!["+backend"]);
+            assert_eq!(edited[0].priority, Some(Priority::A));
+            assert_eq!(edited[0].due_date, NaiveDate::from_ymd_opt(2030, 5, 1));
+            assert_eq!(edited[0].last_changed_by, "carol");
+            assert_eq!(undone, todos);
+        }
+
+        #[test]
+        fn test_import() {
+            let todo_dir = test_todo_dir("brain-import");
+            let file = todo_dir.join("todo.txt");
+            fs::write(
+                &file,
+                "call bob id:abc123\ncall bob\nx 2024-05-01 call bob\n",
+            )
+            .unwrap();
+            let import = args(&["import", "--from", "todotxt", file.to_str().unwrap()]);
+
+            handle_import(&import, todo_dir.clone());
+            let imported = database_get_all_todos(&todo_dir).unwrap();
+            handle_import(&import, todo_dir.clone());
+            let known = database_get_todos(&todo_dir, TodoState::Open)
+                .unwrap()
+                .iter()
+                .filter(|todo| todo.id == "abc123")
+                .count();
+            handle_undo(&args(&["undo", "2"]), todo_dir.clone());
+            let after_undo = database_get_all_todos(&todo_dir).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(imported.len(), 3);
+            let ids: Vec<&String> = imported.iter().map(|todo| &todo.id).collect();
+            assert!(ids.iter().enumerate().all(|(i, id)| !ids[..i].contains(id)));
+            assert_eq!(known, 1);
+            assert!(after_undo.is_empty());
+        }
+
+        #[test]
+        fn test_add() {
+            let Some(todo_dir) = test_todo_repo("brain-add") else {
+                return;
+            };
+            assert!(git(
+                &todo_dir,
+                &["symbolic-ref", "HEAD", "refs/heads/feature/login"]
+            ));
+            assert!(git(
+                &todo_dir,
+                &["commit", "-q", "--allow-empty", "-m", "first"]
+            ));
+            let tags = ["--tag", "side project", "--tag", " ", "--tag", "@bob"];
+            handle_add(
+                &args(&[&["add", "check the branch +git"][..], &tags].concat()),
+                todo_dir.clone(),
+            );
+            let added = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(added[0].git_branch.as_deref(), Some("feature/login"));
+            assert_eq!(
+                added[0].git_commit.as_deref(),
+                Some("50120cf5b24fb87dac1a1aa79348bdd6099fb130")
+            );
+            assert_eq!(added[0].tags, vec!["+git", "+side-project", "@bob"]);
+        }
+
+        #[test]
+        fn test_list_branch() {
+            let Some(todo_dir) = test_todo_repo("brain-list-branch") else {
+                return;
+            };
+            let outside_dir = test_todo_dir("brain-list-outside");
+            let mut todos = test_todos(3);
+            for (todo, branch) in todos.iter_mut().zip([Some("main"), Some("fix"), None]) {
+                todo.git_branch = branch.map(|branch| branch.to_string());
+            }
+            for todo in &todos {
+                database_inserte_todo(&todo_dir, todo.clone()).unwrap();
+            }
+            let listed = |command: &[&str], todo_dir: &Path| {
+                list_todos(&args(command), todo_dir)
+                    .map(|todos| todos.iter().map(|todo| todo.id.clone()).collect::<Vec<_>>())
+            };
+            let named = listed(&["list", "--branch", "fix"], &todo_dir);
+            let filtered = listed(&["list", "branch:main"], &todo_dir);
+            let checked_out = listed(&["list", "--branch"], &todo_dir);
+            // outside a repository there is no checked out branch
+            let outside = listed(&["list", "--branch"], &outside_dir);
+            fs::remove_dir_all(&todo_dir).unwrap();
+            fs::remove_dir_all(&outside_dir).unwrap();
+
+            assert_eq!(named, Ok(vec![todos[1].id.clone()]));
+            assert_eq!(filtered, Ok(vec![todos[0].id.clone()]));
+            assert_eq!(checked_out, Ok(vec![todos[0].id.clone()]));
+            assert!(outside.is_err());
+        }
+
+        #[test]
+        fn test_select_todos_rejects_unknown() {
+            let todos = test_todos(6);
+            for selector in ["6", "3-9", "4-2", "abc", "-1"] {
+                assert!(select_todos(&todos, &[selector.to_string()]).is_err());
+            }
+        }
+    }
 }
 
-mod parse_due_date {
-    use chrono::{self, Datelike, Duration, Local, NaiveDate};
+pub mod parse_due_date {
+    use chrono::{self, Datelike, Duration, Local, NaiveDate, Weekday};
 
     pub fn parse_due_date(due_date_string: String) -> String {
         let today = Local::now();
@@ -402,23 +1295,34 @@ mod parse_due_date {
             }
         }
 
+        if let Some(weekday) = parse_weekday(&due_date_string) {
+            return find_next_weekday(weekday).format("%d.%m.%Y").to_string();
+        }
+
         if parts.len() == 2 && parts[0] == "week" {
             if let Ok(week_number) = parts[1].parse::<u32>() {
                 return parse_week_number(week_number);
             }
         }
 
-        if let Ok(date) = NaiveDate::parse_from_str(&due_date_string, "%d.%m.%Y") {
+        // "%y" first, otherwise "2.4.25" would be read as the year 25
+        if let Ok(date) = NaiveDate::parse_from_str(&due_date_string, "%d.%m.%y") {
             return date.format("%d.%m.%Y").to_string();
         }
 
-        if let Ok(date) = NaiveDate::parse_from_str(&due_date_string, "%d.%m.%y") {
+        if let Ok(date) = NaiveDate::parse_from_str(&due_date_string, "%d.%m.%Y") {
             return date.format("%d.%m.%Y").to_string();
         }
 
-        if let Ok(date) = NaiveDate::parse_from_str(&due_date_string, "%d.%m") {
-            let possible_date = find_next_possible_date(date.day(), date.month());
-            return possible_date.format("%d.%m.%Y").to_string();
+        // chrono can't parse a date without a year, so "%d.%m" is split by hand
+        let day_month: Vec<&str> = due_date_string.split('.').collect();
+        if day_month.len() == 2 {
+            if let (Ok(day), Ok(month)) = (day_month[0].parse::<u32>(), day_month[1].parse::<u32>())
+            {
+                if let Some(possible_date) = find_next_possible_date(day, month) {
+                    return possible_date.format("%d.%m.%Y").to_string();
+                }
+            }
         }
 
         due_date_string
@@ -440,16 +1344,45 @@ mod parse_due_date {
         week_start.format("%d.%m.%Y").to_string()
     }
 
-    fn find_next_possible_date(day: u32, month: u32) -> NaiveDate {
-        let today = Local::today().naive_local();
+    /// "Mo", "mon", "Monday" and everything in between
+    fn parse_weekday(weekday_string: &str) -> Option<Weekday> {
+        let weekday_string = weekday_string.trim().to_lowercase();
+        if weekday_string.len() < 2 {
+            return None;
+        }
+        [
+            ("monday", Weekday::Mon),
+            ("tuesday", Weekday::Tue),
+            ("wednesday", Weekday::Wed),
+            ("thursday", Weekday::Thu),
+            ("friday", Weekday::Fri),
+            ("saturday", Weekday::Sat),
+            ("sunday", Weekday::Sun),
+        ]
+        .into_iter()
+        .find(|(name, _)| name.starts_with(&weekday_string))
+        .map(|(_, weekday)| weekday)
+    }
+
+    /// The next `weekday` after today, so "fri" on a friday is a week later.
+    fn find_next_weekday(weekday: Weekday) -> NaiveDate {
+        let today = Local::now().date_naive();
+        let days =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+        let days = if days == 0 { 7 } else { days };
+        today + Duration::days(days.into())
+    }
+
+    fn find_next_possible_date(day: u32, month: u32) -> Option<NaiveDate> {
+        let today = Local::now().date_naive();
         let year = today.year();
-        let mut date = NaiveDate::from_ymd_opt(year, month, day)
-            .unwrap_or_else(|| NaiveDate::from_ymd(year + 1, month, day));
+        let date = NaiveDate::from_ymd_opt(year, month, day)
+            .or_else(|| NaiveDate::from_ymd_opt(year + 1, month, day))?;
 
         if date < today {
-            date = NaiveDate::from_ymd(year + 1, month, day);
+            return NaiveDate::from_ymd_opt(year + 1, month, day);
         }
-        date
+        Some(date)
     }
 
     #[cfg(test)]
@@ -458,7 +1391,7 @@ mod parse_due_date {
 
         #[test]
         fn test_parse_due_date_relative_days() {
-            let current_date = Local::today().naive_local();
+            let current_date = Local::now().date_naive();
             let expected_date = (current_date + Duration::days(2))
                 .format("%d.%m.%Y")
                 .to_string();
@@ -467,7 +1400,7 @@ mod parse_due_date {
 
         #[test]
         fn test_parse_due_date_named_dates() {
-            let current_date = Local::today().naive_local();
+            let current_date = Local::now().date_naive();
             let expected_tomorrow = (current_date + Duration::days(1))
                 .format("%d.%m.%Y")
                 .to_string();
@@ -480,6 +1413,22 @@ mod parse_due_date {
             assert_eq!(parse_due_date("next week".to_string()), expected_next_week);
         }
 
+        #[test]
+        fn test_parse_due_date_weekdays() {
+            let today = Local::now().date_naive();
+            for name in ["Mo", "mon", "Monday", "fr", "FRI", "friday", "sun"] {
+                let date = NaiveDate::parse_from_str(&parse_due_date(name.to_string()), "%d.%m.%Y")
+                    .unwrap();
+                assert!(date > today && date <= today + Duration::days(7));
+                assert_eq!(
+                    format!("{:?}", date.weekday()).to_lowercase()[..2],
+                    name.to_lowercase()[..2]
+                );
+            }
+            assert_eq!(parse_due_date("m".to_string()), "m");
+            assert_eq!(parse_due_date("mondays".to_string()), "mondays");
+        }
+
         #[test]
         fn test_parse_due_date_specific_dates() {
             assert_eq!(parse_due_date("12.04.2024".to_string()), "12.04.2024");
@@ -489,8 +1438,10 @@ mod parse_due_date {
 
         #[test]
         fn test_parse_due_date_next_possible_day() {
-            let current_date = Local::today().naive_local();
-            let expected_date = find_next_possible_date(2, 3).format("%d.%m.%Y").to_string();
+            let expected_date = find_next_possible_date(2, 3)
+                .unwrap()
+                .format("%d.%m.%Y")
+                .to_string();
             assert_eq!(parse_due_date("02.3".to_string()), expected_date);
         }
 
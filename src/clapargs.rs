@@ -1,9 +1,14 @@
 pub mod clap_args {
 
-    use clap::{command, Arg, ArgMatches, Command};
+    use clap::{command, Arg, ArgAction, ArgMatches, Command};
 
     pub fn setup_cli_args() -> ArgMatches {
-        let arg_matches = command!()
+        cli().get_matches()
+    }
+
+    /// The `todo` command with all its subcommands and arguments.
+    pub fn cli() -> Command {
+        command!()
         .about("This CLI-Tool is to manage Todos. It can be used for global todos in your home dir or in your current working dir.")
         .arg(Arg::new("global").short('g').long("global").required(false).num_args(0).help("use todo in home folder"))
         .subcommands([
@@ -27,9 +32,47 @@ pub mod clap_args {
                 ),
                 Command::new("deleted")
                     .alias("delete")
-                    .arg(Arg::new("delete_methode").help("deletemethode: in_file/delete\nin_file saves deleted todos in .todo.deleted\ndelete doesn't save deleted todos").required(false)),
+                    .arg(Arg::new("delete_method").help("deletemethode: in_file/delete\nin_file saves deleted todos in .todo.deleted\ndelete doesn't save deleted todos").required(true)),
+                Command::new("storage")
+                    .arg(Arg::new("storage").help("how the todos are stored: tab/jsonl/sqlite\ntab writes tab separated lines\njsonl writes one json object per line\nsqlite keeps all todos in .todo.db").required(true)),
             ]),
-            Command::new("delete").arg(Arg::new("index").required(false)),
+            Command::new("delete").arg(
+                Arg::new("index")
+                    .help("ids, indices or ranges like 5-8 of the todos, - reads them from stdin")
+                    .num_args(1..)
+                    .required(false),
+            ),
+            Command::new("edit")
+                .about("changes description, due date, due time or priority of a todo")
+                .args([
+                    Arg::new("index").help("id or index of the todo").required(true),
+                    Arg::new("description").long("description").required(false),
+                    Arg::new("due_date")
+                        .short('d')
+                        .long("due-date")
+                        .aliases(["duedate"])
+                        .help("new due date, `none` removes it")
+                        .required(false),
+                    Arg::new("due_time")
+                        .short('t')
+                        .long("due-time")
+                        .aliases(["duetime"])
+                        .help("new due time, `none` removes it")
+                        .required(false),
+                    Arg::new("priority")
+                        .short('p')
+                        .long("priority")
+                        .help("new priority, `none` removes it")
+                        .value_parser(["a", "b", "c", "d", "high", "medium", "low", "none"])
+                        .ignore_case(true)
+                        .required(false),
+                ]),
+            Command::new("reopen")
+                .about("lists finished todos or opens one again")
+                .arg(Arg::new("index").help("id or index of the finished todo").required(false)),
+            Command::new("restore")
+                .about("lists deleted todos or moves one back")
+                .arg(Arg::new("index").help("id or index of the deleted todo").required(false)),
             Command::new("add").args([
                 Arg::new("description").required(true),
                 Arg::new("due_date")
@@ -42,13 +85,133 @@ pub mod clap_args {
                     .long("due-time")
                     .aliases(["duetime"])
                     .required(false),
+                Arg::new("priority")
+                    .short('p')
+                    .long("priority")
+                    .help("a/b/c/d or high/medium/low")
+                    .value_parser(["a", "b", "c", "d", "high", "medium", "low"])
+                    .ignore_case(true)
+                    .required(false),
+                Arg::new("tag")
+                    .long("tag")
+                    .help("adds a tag like backend (+backend) or @alice, can be given more than once, spaces become -\n+words and @words in the description are tags too")
+                    .action(ArgAction::Append)
+                    .required(false),
+            ]),
+            Command::new("tags").about("lists the tags of the open todos and how often they are used"),
+            Command::new("list").alias("ls").arg(
+                Arg::new("filter")
+                    .help("only lists matching todos, e.g. `due<fri and not by:alice`\nterms: due/created/done/changed with : < <= > >= (dates like -d or none)\nby/changedby/id/branch with : or ~, text~word, status:open/done/deleted, priority:a-d/none, +tag or @tag\ncombined with and/or/not and ( )")
+                    .num_args(1..)
+                    .required(false),
+            )
+            .args([
+                Arg::new("sort")
+                    .long("sort")
+                    .help("sorts the todos, todos without due date go last when sorting by due")
+                    .value_parser(["due", "created", "creator", "description", "priority"]),
+                Arg::new("order")
+                    .long("order")
+                    .help("order of --sort")
+                    .value_parser(["asc", "desc"])
+                    .default_value("asc"),
+                Arg::new("format")
+                    .long("format")
+                    .help("prints every field of the todos instead of the list")
+                    .value_parser(["json", "csv", "markdown"]),
+                Arg::new("branch")
+                    .long("branch")
+                    .help("only lists todos added on this git branch, the checked out one without name")
+                    .num_args(0..=1)
+                    .default_missing_value("HEAD"),
             ]),
-            Command::new("list").alias("ls"),
-            Command::new("finish").arg(Arg::new("index")),
+            Command::new("import")
+                .about("adds the todos from a file of another todo tool")
+                .args([
+                    Arg::new("from")
+                        .long("from")
+                        .help("format of the file")
+                        .value_parser(["todotxt", "ics"])
+                        .required(true),
+                    Arg::new("file").required(true),
+                ]),
+            Command::new("export")
+                .about("prints the todos for another todo tool or calendar")
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .help("format to print")
+                        .value_parser(["todotxt", "ics"])
+                        .required(true),
+                ),
+            Command::new("sync-md")
+                .about("syncs the todos with a markdown checklist like TODO.md")
+                .arg(Arg::new("file").required(true)),
+            Command::new("scan")
+                .about("adds a todo for every TODO, FIXME and XXX comment in the project")
+                .args([
+                    Arg::new("path").help("only scans this file or directory"),
+                    Arg::new("finish")
+                        .long("finish")
+                        .help("finishes todos whose comment is gone")
+                        .action(ArgAction::SetTrue),
+                ]),
+            Command::new("hooks")
+                .about("git hooks that warn about overdue and +blocking todos")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("install")
+                        .about("installs pre-commit, pre-push and post-commit hooks in the git repository\na `Closes-Todo: {id}` line in a commit message finishes the todo")
+                        .arg(
+                            Arg::new("block")
+                                .long("block")
+                                .help("stops commits and pushes instead of only warning")
+                                .action(ArgAction::SetTrue),
+                        ),
+                    Command::new("run")
+                        .about("what the installed hooks run")
+                        .hide(true)
+                        .args([
+                            Arg::new("hook")
+                                .value_parser(["pre-commit", "pre-push", "post-commit"])
+                                .required(true),
+                            Arg::new("block").long("block").action(ArgAction::SetTrue),
+                        ]),
+                ]),
+            Command::new("merge-driver")
+                .about("merges .todo.* files todo by todo when git merges branches\nwithout files it sets itself up as merge driver in git and .gitattributes")
+                .args([
+                    Arg::new("base").help("%O, the common version").requires_all(["ours", "theirs"]),
+                    Arg::new("ours").help("%A, our version, the result is written here"),
+                    Arg::new("theirs").help("%B, their version"),
+                ]),
+            Command::new("migrate").about("moves all todos into another storage").arg(
+                Arg::new("to")
+                    .long("to")
+                    .help("tab/jsonl/sqlite")
+                    .required(true),
+            ),
+            Command::new("finish").arg(
+                Arg::new("index")
+                    .help("ids, indices or ranges like 5-8 of the todos, - reads them from stdin")
+                    .num_args(1..),
+            ),
+            Command::new("undo")
+                .about("reverses the last add, finish, delete or edit")
+                .arg(
+                    Arg::new("count")
+                        .help("how many operations to undo")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1"),
+                ),
+            Command::new("redo")
+                .about("replays operations reversed by undo")
+                .arg(
+                    Arg::new("count")
+                        .help("how many operations to redo")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1"),
+                ),
         ])
-        .get_matches();
-
-        #[warn(clippy::needless_return)]
-        return arg_matches;
     }
 }
@@ -1,77 +1,299 @@
 pub mod database_handler {
     pub mod todo_database {
-        use std::{any::Any, str::FromStr};
+        use std::{
+            collections::{hash_map::DefaultHasher, HashSet},
+            hash::{Hash, Hasher},
+            mem::discriminant,
+            path::{Path, PathBuf},
+        };
 
-        use chrono::{NaiveDate, NaiveTime, Utc};
+        use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+        use serde::{Deserialize, Serialize};
 
-        #[derive(Debug)]
+        use crate::storagehandler::storage_handler::get_storage;
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        #[serde(rename_all = "lowercase")]
         pub enum TodoState {
             Open,
             Done,
             Deleted,
         }
+        impl TodoState {
+            /// name of the file the todos with this state are stored in
+            pub fn file_name(&self) -> &'static str {
+                match self {
+                    TodoState::Open => ".todo.todo",
+                    TodoState::Done => ".todo.finished",
+                    TodoState::Deleted => ".todo.deleted",
+                }
+            }
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    TodoState::Open => "open",
+                    TodoState::Done => "done",
+                    TodoState::Deleted => "deleted",
+                }
+            }
+
+            pub fn from_str(state: &str) -> Option<TodoState> {
+                match state {
+                    "open" => Some(TodoState::Open),
+                    "done" => Some(TodoState::Done),
+                    "deleted" => Some(TodoState::Deleted),
+                    _ => None,
+                }
+            }
+
+            pub fn file_path(&self, todo_dir: &Path) -> PathBuf {
+                let mut path = todo_dir.to_path_buf();
+                path.push(self.file_name());
+                path
+            }
+        }
+
+        /// `A` is the highest priority, `D` the lowest.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+        pub enum Priority {
+            A,
+            B,
+            C,
+            D,
+        }
+        impl Priority {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    Priority::A => "A",
+                    Priority::B => "B",
+                    Priority::C => "C",
+                    Priority::D => "D",
+                }
+            }
+
+            /// `A` to `D` in any case, or `high`, `medium` and `low` for `A`, `B` and `C`.
+            pub fn from_str(priority: &str) -> Option<Priority> {
+                match priority.trim().to_lowercase().as_str() {
+                    "a" | "high" => Some(Priority::A),
+                    "b" | "medium" => Some(Priority::B),
+                    "c" | "low" => Some(Priority::C),
+                    "d" => Some(Priority::D),
+                    _ => None,
+                }
+            }
+        }
+
+        /// Returns the `+project` and `@context` words of `description`, each once.
+        pub fn tags_in(description: &str) -> Vec<String> {
+            let mut tags: Vec<String> = Vec::new();
+            for word in description.split_whitespace() {
+                if word.len() > 1 && word.starts_with(['+', '@']) && !tags.iter().any(|t| t == word)
+                {
+                    tags.push(word.to_string());
+                }
+            }
+            tags
+        }
+
+        /// The tag for a `--tag` value or a category of another app, e.g. `back end` is
+        /// `+back-end`. Whitespace becomes `-`, tags are single words like the ones in
+        /// descriptions. `None` if nothing is left.
+        pub fn to_tag(name: &str) -> Option<String> {
+            let name = name.split_whitespace().collect::<Vec<&str>>().join("-");
+            let tag = if name.starts_with(['+', '@']) {
+                name
+            } else {
+                format!("+{}", name)
+            };
+            (tag.len() > 1).then_some(tag)
+        }
+
+        /// Version of the format of the `.todo.*` files. Files without header are version 1.
+        ///
+        /// Version 3 added optional fields after `last_change_date`. Missing ones are empty
+        /// and unknown ones are ignored, so new optional fields don't need a new version.
+        pub const FORMAT_VERSION: u32 = 3;
+
+        const ID_LENGTH: u32 = 6;
+        const ID_ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
 
-        #[derive(Debug)]
+        /// Builds a short id out of `seed`.
+        ///
+        /// Ids always contain a letter, so they can't be mistaken for an index.
+        pub fn generate_id(seed: &str) -> String {
+            let mut salt: u64 = 0;
+            loop {
+                let mut hasher = DefaultHasher::new();
+                seed.hash(&mut hasher);
+                salt.hash(&mut hasher);
+                let mut hash = hasher.finish() % 36u64.pow(ID_LENGTH);
+
+                let mut id = String::new();
+                for _ in 0..ID_LENGTH {
+                    id.push(ID_ALPHABET[(hash % 36) as usize] as char);
+                    hash /= 36;
+                }
+                if id.chars().any(|c| c.is_ascii_alphabetic()) {
+                    return id;
+                }
+                salt += 1;
+            }
+        }
+
+        /// Whether `id` looks like one `generate_id` builds, e.g. for ids of imported todos.
+        pub fn is_valid_id(id: &str) -> bool {
+            id.len() == ID_LENGTH as usize
+                && id.bytes().all(|c| ID_ALPHABET.contains(&c))
+                && id.chars().any(|c| c.is_ascii_alphabetic())
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
         pub struct Todo {
-            description: String,
-            status: TodoState,
-            due_date: NaiveDate,
-            due_time: NaiveTime,
-            done_date: Option<NaiveDate>,
-            create_date: NaiveDate,
-            created_by: String,
-            last_changed_by: String,
-            last_change_date: NaiveDate,
+            pub id: String,
+            pub description: String,
+            pub status: TodoState,
+            pub due_date: Option<NaiveDate>,
+            pub due_time: Option<NaiveTime>,
+            pub done_date: Option<NaiveDate>,
+            pub create_date: NaiveDateTime,
+            pub created_by: String,
+            pub last_changed_by: String,
+            pub last_change_date: NaiveDate,
+            /// Where the todo comes from, e.g. `src/main.rs:12` for a `TODO` comment.
+            #[serde(default)]
+            pub source: Option<String>,
+            /// Branch that was checked out when the todo was added.
+            #[serde(default)]
+            pub git_branch: Option<String>,
+            /// HEAD commit when the todo was added.
+            #[serde(default)]
+            pub git_commit: Option<String>,
+            #[serde(default)]
+            pub priority: Option<Priority>,
+            /// Tags like `+backend` or `@alice`, the ones in the description and the ones
+            /// given with `--tag`.
+            #[serde(default)]
+            pub tags: Vec<String>,
         }
         impl Todo {
-            fn new(
+            pub fn new(
                 description: String,
                 status: TodoState,
-                due_date: NaiveDate,
-                due_time: NaiveTime,
-                create_date: NaiveDate,
+                due_date: Option<NaiveDate>,
+                due_time: Option<NaiveTime>,
+                create_date: NaiveDateTime,
                 created_by: String,
             ) -> Todo {
-                let today = Utc::now().date_naive();
+                let now = Local::now();
+                let today = now.date_naive();
+                let id = generate_id(&format!(
+                    "{}{}{}",
+                    description,
+                    created_by,
+                    now.timestamp_nanos_opt().unwrap_or_default()
+                ));
                 Todo {
+                    id,
+                    tags: tags_in(&description),
                     description,
                     status,
                     due_date,
                     due_time,
                     done_date: None,
-                    create_date,
+                    // the storages only keep whole seconds
+                    create_date: create_date.with_nanosecond(0).unwrap_or(create_date),
                     created_by: created_by.clone(),
                     last_changed_by: created_by,
                     last_change_date: today,
+                    source: None,
+                    git_branch: None,
+                    git_commit: None,
+                    priority: None,
                 }
             }
         }
 
+        /// A field of `Todo` with a value, used to query the storage and to change fields.
+        #[derive(Debug, Clone)]
         pub enum DatabaseField {
+            Id(String),
             Description(String),
             Status(TodoState),
-            DueDate(NaiveDate),
-            DueTime(NaiveTime),
+            DueDate(Option<NaiveDate>),
+            DueTime(Option<NaiveTime>),
             DoneDate(Option<NaiveDate>),
-            CreateDate(NaiveDate),
+            CreateDate(NaiveDateTime),
             CreatedBy(String),
             LastChangedBy(String),
             LastChangeDate(NaiveDate),
+            Source(Option<String>),
+            GitBranch(Option<String>),
+            GitCommit(Option<String>),
+            Priority(Option<Priority>),
+            Tags(Vec<String>),
         }
         impl DatabaseField {
-            pub fn as_str(&self) -> String {
-                let db_field_str = match self {
-                    DatabaseField::Description(_x) => "description",
-                    DatabaseField::Status(_x) => "status",
-                    DatabaseField::DueDate(_x) => "due_date",
-                    DatabaseField::DueTime(_x) => "due_time",
-                    DatabaseField::DoneDate(_x) => "done_date",
-                    DatabaseField::CreateDate(_x) => "create_date",
-                    DatabaseField::CreatedBy(_x) => "created_by",
-                    DatabaseField::LastChangedBy(_x) => "last_changed_by",
-                    DatabaseField::LastChangeDate(_x) => "last_change_date",
-                };
-                db_field_str.to_string()
+            /// Name of the field, the same as the JSON key and the sqlite column.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    DatabaseField::Id(_) => "id",
+                    DatabaseField::Description(_) => "description",
+                    DatabaseField::Status(_) => "status",
+                    DatabaseField::DueDate(_) => "due_date",
+                    DatabaseField::DueTime(_) => "due_time",
+                    DatabaseField::DoneDate(_) => "done_date",
+                    DatabaseField::CreateDate(_) => "create_date",
+                    DatabaseField::CreatedBy(_) => "created_by",
+                    DatabaseField::LastChangedBy(_) => "last_changed_by",
+                    DatabaseField::LastChangeDate(_) => "last_change_date",
+                    DatabaseField::Source(_) => "source",
+                    DatabaseField::GitBranch(_) => "git_branch",
+                    DatabaseField::GitCommit(_) => "git_commit",
+                    DatabaseField::Priority(_) => "priority",
+                    DatabaseField::Tags(_) => "tags",
+                }
+            }
+
+            /// checks if the field of `todo` has the value of this field
+            pub fn matches(&self, todo: &Todo) -> bool {
+                match self {
+                    DatabaseField::Id(x) => &todo.id == x,
+                    DatabaseField::Description(x) => &todo.description == x,
+                    DatabaseField::Status(x) => &todo.status == x,
+                    DatabaseField::DueDate(x) => &todo.due_date == x,
+                    DatabaseField::DueTime(x) => &todo.due_time == x,
+                    DatabaseField::DoneDate(x) => &todo.done_date == x,
+                    DatabaseField::CreateDate(x) => &todo.create_date == x,
+                    DatabaseField::CreatedBy(x) => &todo.created_by == x,
+                    DatabaseField::LastChangedBy(x) => &todo.last_changed_by == x,
+                    DatabaseField::LastChangeDate(x) => &todo.last_change_date == x,
+                    DatabaseField::Source(x) => &todo.source == x,
+                    DatabaseField::GitBranch(x) => &todo.git_branch == x,
+                    DatabaseField::GitCommit(x) => &todo.git_commit == x,
+                    DatabaseField::Priority(x) => &todo.priority == x,
+                    DatabaseField::Tags(x) => &todo.tags == x,
+                }
+            }
+
+            /// sets the field of `todo` to the value of this field
+            pub fn apply(&self, todo: &mut Todo) {
+                match self {
+                    DatabaseField::Id(x) => todo.id = x.clone(),
+                    DatabaseField::Description(x) => todo.description = x.clone(),
+                    DatabaseField::Status(x) => todo.status = x.clone(),
+                    DatabaseField::DueDate(x) => todo.due_date = *x,
+                    DatabaseField::DueTime(x) => todo.due_time = *x,
+                    DatabaseField::DoneDate(x) => todo.done_date = *x,
+                    DatabaseField::CreateDate(x) => todo.create_date = *x,
+                    DatabaseField::CreatedBy(x) => todo.created_by = x.clone(),
+                    DatabaseField::LastChangedBy(x) => todo.last_changed_by = x.clone(),
+                    DatabaseField::LastChangeDate(x) => todo.last_change_date = *x,
+                    DatabaseField::Source(x) => todo.source = x.clone(),
+                    DatabaseField::GitBranch(x) => todo.git_branch = x.clone(),
+                    DatabaseField::GitCommit(x) => todo.git_commit = x.clone(),
+                    DatabaseField::Priority(x) => todo.priority = *x,
+                    DatabaseField::Tags(x) => todo.tags = x.clone(),
+                }
             }
         }
 
@@ -80,41 +302,65 @@ pub mod database_handler {
             Delete,
         }
 
-        /// Retrieves a `Todo` item from the database based on specified fields.
+        /// Returns every todo regardless of its state.
+        pub fn database_get_all_todos(todo_dir: &Path) -> Result<Vec<Todo>, String> {
+            let mut todos = Vec::new();
+            for status in [TodoState::Open, TodoState::Done, TodoState::Deleted] {
+                todos.extend(database_get_todos(todo_dir, status)?);
+            }
+            Ok(todos)
+        }
+
+        /// Returns all todos with the given state in the order they were added.
+        pub fn database_get_todos(todo_dir: &Path, status: TodoState) -> Result<Vec<Todo>, String> {
+            get_storage(todo_dir).load(&status)
+        }
+
+        /// Retrieves the todos that match all of the specified fields.
         ///
         /// # Arguments
         ///
-        /// * `fields` - A vector of `DatabaseField` items used to specify which fields
-        ///   to retrieve. Each field must be unique by type.
+        /// * `todo_dir` - The directory containing the `.todo.*` files.
+        /// * `fields` - A vector of `DatabaseField` items the todos must match. Each field
+        ///   must be unique by type. Without fields every todo matches.
         ///
         /// # Errors
         ///
-        /// Returns an error if any of the fields are specified more than once.
-        /// The error message includes a list of strings, each one describing a duplication issue.
+        /// Returns an error if any of the fields are specified more than once, or if no todo
+        /// matches. The error is a list of strings, each one describing a problem.
         ///
         /// # Examples
         ///
         /// ```
-        /// # use todo_database::database_get_todo;
-        /// # use todo_database::DatabaseField;
+        /// # use todo_cli::databasehandler::database_handler::todo_database::{
+        /// #     database_get_todo, database_inserte_todo, DatabaseField, Todo, TodoState,
+        /// # };
         /// # fn main() -> Result<(), Vec<String>> {
-        /// let fields = vec![DatabaseField::Description("Hello World!"), DatabaseField::CreateDate("20.04.2024"), DatabaseField::Status("done")];
-        /// let todo = database_get_todo(fields);
+        /// # let todo_dir = std::env::temp_dir().join(format!("todo-cli-doc-{}", std::process::id()));
+        /// # std::fs::create_dir_all(&todo_dir).unwrap();
+        /// # let now = chrono::Local::now().naive_local();
+        /// # let todo = Todo::new("Hello World!".to_string(), TodoState::Done, None, None, now, "alice".to_string());
+        /// # database_inserte_todo(&todo_dir, todo).unwrap();
+        /// let fields = vec![DatabaseField::Description("Hello World!".to_string()), DatabaseField::Status(TodoState::Done)];
+        /// let todo = database_get_todo(&todo_dir, fields);
         /// match todo {
         ///     Ok(todo) => println!("Retrieved todo: {:?}", todo),
         ///     Err(e) => e.iter().for_each(|error| println!("Error: {}", error)),
         /// }
+        /// # std::fs::remove_dir_all(&todo_dir).unwrap();
         /// # Ok(())
         /// # }
         /// ```
-        pub fn database_get_todo(fields: Vec<DatabaseField>) -> Result<Vec<Todo>, Vec<String>> {
+        pub fn database_get_todo(
+            todo_dir: &Path,
+            fields: Vec<DatabaseField>,
+        ) -> Result<Vec<Todo>, Vec<String>> {
             let mut err: Vec<String> = Vec::new();
-            let mut todos: Vec<Todo> = Vec::new();
 
             // check double field
-            for field in &fields {
-                for field2 in &fields {
-                    if field.type_id() == field2.type_id() {
+            for (i, field) in fields.iter().enumerate() {
+                for field2 in &fields[i + 1..] {
+                    if discriminant(field) == discriminant(field2) {
                         err.push(format!(
                             "you can't specify the same field twise! field: {}",
                             field.as_str()
@@ -123,56 +369,531 @@ pub mod database_handler {
                 }
             }
             // return if err before making request
-            if err.is_empty() {
+            if !err.is_empty() {
                 return Err(err);
             }
-            // request db
-            todo!("implement database_get_todo");
+            // request db, the first field narrows down the todos in the storage
+            let stored = match fields.first() {
+                Some(field) => get_storage(todo_dir).query(field),
+                None => database_get_all_todos(todo_dir),
+            }
+            .map_err(|e| vec![e])?;
+            let todos: Vec<Todo> = stored
+                .into_iter()
+                .filter(|todo| fields.iter().all(|field| field.matches(todo)))
+                .collect();
 
             if todos.is_empty() {
                 err.push("No todo with given fields was found!".to_string());
                 return Err(err);
             }
-            return Ok(todos);
+            Ok(todos)
+        }
+
+        /// Stores `todo`. Returns it as it was stored, its id is changed if it was taken.
+        pub fn database_inserte_todo(todo_dir: &Path, mut todo: Todo) -> Result<Todo, String> {
+            let storage = get_storage(todo_dir);
+            // the sqlite storage looks ids up by its primary key
+            while !storage
+                .query(&DatabaseField::Id(todo.id.clone()))?
+                .is_empty()
+            {
+                todo.id = generate_id(&todo.id);
+            }
+            storage.insert(&todo)?;
+            Ok(todo)
+        }
+
+        pub fn database_change_todo(
+            todo_dir: &Path,
+            old_todo: Todo,
+            new_todo: Todo,
+        ) -> Result<Todo, String> {
+            get_storage(todo_dir).update(&old_todo, &new_todo)?;
+            Ok(new_todo)
+        }
+
+        /// Sets `fields` on `todo` and marks it as changed by `changed_by` today, all in one
+        /// write. Returns the todo as it was stored.
+        #[allow(non_snake_case)]
+        pub fn database_change_todo_Field(
+            todo_dir: &Path,
+            todo: Todo,
+            fields: Vec<DatabaseField>,
+            changed_by: String,
+        ) -> Result<Todo, String> {
+            let mut changed = todo.clone();
+            let last_change = [
+                DatabaseField::LastChangedBy(changed_by),
+                DatabaseField::LastChangeDate(Local::now().date_naive()),
+            ];
+            for field in fields.iter().chain(&last_change) {
+                field.apply(&mut changed);
+            }
+            database_change_todo(todo_dir, todo, changed)
         }
 
-        pub fn database_inserte_todo(todo: Todo) -> Result<(), String> {
-            todo!("implement database_inserte_todo");
+        /// Sets `new_value` on every todo that matches `equ`, all in one write. Returns the
+        /// changed todos, none if no todo matches.
+        pub fn database_change_todo_where(
+            todo_dir: &Path,
+            equ: DatabaseField,
+            new_value: DatabaseField,
+        ) -> Result<Vec<Todo>, String> {
+            let changes: Vec<(Todo, Option<Todo>)> = get_storage(todo_dir)
+                .query(&equ)?
+                .into_iter()
+                .map(|todo| {
+                    let mut new_todo = todo.clone();
+                    new_value.apply(&mut new_todo);
+                    (todo, Some(new_todo))
+                })
+                .collect();
+            Ok(database_change_todos(todo_dir, changes)?
+                .into_iter()
+                .flatten()
+                .collect())
         }
-        pub fn database_change_todo(old_todo: Todo, new_todo: Todo) -> Result<(), String> {
-            todo!("implement database_change_todo");
+
+        /// Replaces every old todo with its new todo, or removes it if there is none.
+        /// Either all changes are stored or none.
+        pub fn database_change_todos(
+            todo_dir: &Path,
+            changes: Vec<(Todo, Option<Todo>)>,
+        ) -> Result<Vec<Option<Todo>>, String> {
+            let changes: Vec<(Option<Todo>, Option<Todo>)> = changes
+                .into_iter()
+                .map(|(old_todo, new_todo)| (Some(old_todo), new_todo))
+                .collect();
+            get_storage(todo_dir).update_all(&changes)?;
+            Ok(changes.into_iter().map(|(_, new_todo)| new_todo).collect())
+        }
+
+        /// Like `database_change_todos`, but a change without old todo adds its new todo.
+        /// Added todos get another id if theirs is taken, by a stored todo or an earlier added
+        /// one. Returns the new todos as they were stored.
+        pub fn database_apply_changes(
+            todo_dir: &Path,
+            changes: Vec<(Option<Todo>, Option<Todo>)>,
+        ) -> Result<Vec<Option<Todo>>, String> {
+            let mut taken: HashSet<String> = database_get_all_todos(todo_dir)?
+                .into_iter()
+                .map(|todo| todo.id)
+                .collect();
+            let changes: Vec<(Option<Todo>, Option<Todo>)> = changes
+                .into_iter()
+                .map(|(old_todo, new_todo)| match (old_todo, new_todo) {
+                    (None, Some(mut added)) => {
+                        while taken.contains(&added.id) {
+                            added.id = generate_id(&added.id);
+                        }
+                        taken.insert(added.id.clone());
+                        (None, Some(added))
+                    }
+                    change => change,
+                })
+                .collect();
+            get_storage(todo_dir).update_all(&changes)?;
+            Ok(changes.into_iter().map(|(_, new_todo)| new_todo).collect())
         }
-        pub fn database_change_todo_Field<T: PartialEq + Clone>(
+
+        /// Deletes `todo`. Returns it as it was stored, `None` if it was removed.
+        pub fn database_delete_todo(
+            todo_dir: &Path,
             todo: Todo,
-            field: DatabaseField,
-            new_value: T,
-        ) -> Result<(), String> {
-            todo!("implement database_change_todo_where");
+            del_method: DeletionMethod,
+            changed_by: String,
+        ) -> Result<Option<Todo>, String> {
+            Ok(database_delete_todos(todo_dir, vec![todo], del_method, changed_by)?.remove(0))
         }
-        pub fn database_change_todo_where<T: PartialEq + Clone>(
-            field: DatabaseField,
-            equ: T,
-            new_value: T,
-        ) -> Result<(), String> {
-            todo!("implement database_change_todo_where");
+
+        /// Deletes all `todos` at once. Returns them as they were stored, `None` if removed.
+        pub fn database_delete_todos(
+            todo_dir: &Path,
+            todos: Vec<Todo>,
+            del_method: DeletionMethod,
+            changed_by: String,
+        ) -> Result<Vec<Option<Todo>>, String> {
+            let changes = todos
+                .into_iter()
+                .map(|todo| {
+                    let new_todo = match del_method {
+                        DeletionMethod::Mark => Some(deleted(todo.clone(), changed_by.clone())),
+                        DeletionMethod::Delete => None,
+                    };
+                    (todo, new_todo)
+                })
+                .collect();
+            database_change_todos(todo_dir, changes)
         }
 
-        pub fn database_delete_todo(todo: Todo, delMethod: DeletionMethod) -> Result<(), String> {
-            match delMethod {
-                DeletionMethod::Mark => todo!(),
-                DeletionMethod::Delete => todo!(),
-            }
-            todo!("implement database_delete_todo");
+        fn deleted(mut todo: Todo, changed_by: String) -> Todo {
+            todo.status = TodoState::Deleted;
+            todo.last_changed_by = changed_by;
+            todo.last_change_date = Local::now().date_naive();
+            todo
         }
-        pub fn database_undelete_todo(todo: Todo) -> Result<(), String> {
-            todo!("implement database_undelete_todo");
+        pub fn database_undelete_todo(
+            todo_dir: &Path,
+            todo: Todo,
+            changed_by: String,
+        ) -> Result<Todo, String> {
+            let mut restored = todo.clone();
+            restored.status = TodoState::Open;
+            restored.last_changed_by = changed_by;
+            restored.last_change_date = Local::now().date_naive();
+            database_change_todo(todo_dir, todo, restored)
+        }
+
+        pub fn database_finish_todo(
+            todo_dir: &Path,
+            todo: Todo,
+            changed_by: String,
+        ) -> Result<Todo, String> {
+            database_change_todo(todo_dir, todo.clone(), finished(todo, changed_by))
         }
 
-        pub fn database_finish_todo(todo: Todo) -> Result<(), String> {
-            todo!("implement database_finish_todo");
+        /// Finishes all `todos` at once. Returns them as they were stored.
+        pub fn database_finish_todos(
+            todo_dir: &Path,
+            todos: Vec<Todo>,
+            changed_by: String,
+        ) -> Result<Vec<Todo>, String> {
+            let changes = todos
+                .into_iter()
+                .map(|todo| (todo.clone(), Some(finished(todo, changed_by.clone()))))
+                .collect();
+            Ok(database_change_todos(todo_dir, changes)?
+                .into_iter()
+                .flatten()
+                .collect())
         }
-        pub fn database_unfinish_todo(todo: Todo) -> Result<(), String> {
-            todo!("implement database_unfinish_todo");
+
+        /// `todo` as finished by `changed_by` today.
+        pub fn finished(mut todo: Todo, changed_by: String) -> Todo {
+            let today = Local::now().date_naive();
+            todo.status = TodoState::Done;
+            todo.last_changed_by = changed_by;
+            todo.done_date = Some(today);
+            todo.last_change_date = today;
+            todo
+        }
+        pub fn database_unfinish_todo(
+            todo_dir: &Path,
+            todo: Todo,
+            changed_by: String,
+        ) -> Result<Todo, String> {
+            let mut reopened = todo.clone();
+            reopened.status = TodoState::Open;
+            reopened.last_changed_by = changed_by;
+            reopened.done_date = None;
+            reopened.last_change_date = Local::now().date_naive();
+            database_change_todo(todo_dir, todo, reopened)
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::testhelper::test_helper::{test_dir, test_todo};
+
+            #[test]
+            fn test_generate_id_is_short_and_not_a_number() {
+                for seed in ["", "a", "1", "some todo", "another todo"] {
+                    let id = generate_id(seed);
+                    assert_eq!(id.len(), ID_LENGTH as usize);
+                    assert!(id.parse::<usize>().is_err());
+                    assert_eq!(id, generate_id(seed));
+                    assert!(is_valid_id(&id));
+                }
+                for id in [
+                    "", "abc12", "abc1234", "123456", "ABC123", "ab 123", "ab-123",
+                ] {
+                    assert!(!is_valid_id(id), "{}", id);
+                }
+            }
+
+            #[test]
+            fn test_tags_in() {
+                assert_eq!(
+                    tags_in("call @bob about +backend + and +backend again, mail@example.com"),
+                    vec!["@bob", "+backend"]
+                );
+                assert!(tags_in("no tags").is_empty());
+            }
+
+            #[test]
+            fn test_to_tag() {
+                assert_eq!(to_tag("backend").as_deref(), Some("+backend"));
+                assert_eq!(to_tag("@alice").as_deref(), Some("@alice"));
+                assert_eq!(to_tag(" back\tend  api ").as_deref(), Some("+back-end-api"));
+                assert_eq!(to_tag(" "), None);
+                assert_eq!(to_tag("+"), None);
+            }
+
+            fn descriptions(todo_dir: &Path, status: TodoState) -> Vec<String> {
+                database_get_todos(todo_dir, status)
+                    .unwrap()
+                    .into_iter()
+                    .map(|todo| todo.description)
+                    .collect()
+            }
+
+            #[test]
+            fn test_insert_gives_taken_ids_a_new_one() {
+                let todo_dir = test_dir("db-insert");
+                let first = database_inserte_todo(&todo_dir, test_todo("first")).unwrap();
+                let mut second = test_todo("second");
+                second.id = first.id.clone();
+                let second = database_inserte_todo(&todo_dir, second).unwrap();
+                let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                assert_ne!(first.id, second.id);
+                assert_eq!(open, vec![first, second]);
+            }
+
+            #[test]
+            fn test_apply_changes_adds_with_free_ids() {
+                let todo_dir = test_dir("db-apply");
+                let first = database_inserte_todo(&todo_dir, test_todo("first")).unwrap();
+                let mut added = test_todo("added");
+                added.id = first.id.clone();
+                let applied = database_apply_changes(
+                    &todo_dir,
+                    vec![
+                        (None, Some(added.clone())),
+                        (
+                            Some(first.clone()),
+                            Some(finished(first.clone(), "bob".to_string())),
+                        ),
+                        (None, Some(added)),
+                    ],
+                )
+                .unwrap();
+                let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+                let done = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                let ids: HashSet<&String> = applied.iter().flatten().map(|todo| &todo.id).collect();
+                assert_eq!(ids.len(), 3);
+                assert_eq!(
+                    open,
+                    vec![applied[0].clone().unwrap(), applied[2].clone().unwrap()]
+                );
+                assert_eq!(done, vec![applied[1].clone().unwrap()]);
+            }
+
+            #[test]
+            fn test_change_and_state_moves() {
+                let todo_dir = test_dir("db-moves");
+                let first = database_inserte_todo(&todo_dir, test_todo("first")).unwrap();
+                let second = database_inserte_todo(&todo_dir, test_todo("second")).unwrap();
+                let third = database_inserte_todo(&todo_dir, test_todo("third")).unwrap();
+
+                let mut changed = first.clone();
+                changed.description = "first, changed".to_string();
+                let changed = database_change_todo(&todo_dir, first, changed).unwrap();
+                let finished = database_finish_todo(&todo_dir, second, "bob".to_string()).unwrap();
+                let deleted = database_delete_todos(
+                    &todo_dir,
+                    vec![third],
+                    DeletionMethod::Mark,
+                    "bob".to_string(),
+                )
+                .unwrap();
+                let after_moves = [
+                    descriptions(&todo_dir, TodoState::Open),
+                    descriptions(&todo_dir, TodoState::Done),
+                    descriptions(&todo_dir, TodoState::Deleted),
+                ];
+
+                let reopened =
+                    database_unfinish_todo(&todo_dir, finished.clone(), "carol".to_string())
+                        .unwrap();
+                let restored = database_undelete_todo(
+                    &todo_dir,
+                    deleted[0].clone().unwrap(),
+                    "carol".to_string(),
+                )
+                .unwrap();
+                database_delete_todos(
+                    &todo_dir,
+                    vec![changed.clone()],
+                    DeletionMethod::Delete,
+                    "carol".to_string(),
+                )
+                .unwrap();
+                let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+                let all = database_get_all_todos(&todo_dir).unwrap();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                assert_eq!(
+                    after_moves,
+                    [vec!["first, changed"], vec!["second"], vec!["third"]]
+                );
+                assert_eq!(finished.status, TodoState::Done);
+                assert!(finished.done_date.is_some());
+                assert_eq!(finished.last_changed_by, "bob");
+                assert_eq!(deleted[0].as_ref().unwrap().status, TodoState::Deleted);
+                assert_eq!(reopened.done_date, None);
+                assert_eq!(restored.last_changed_by, "carol");
+                assert_eq!(open, vec![reopened, restored]);
+                assert_eq!(all.len(), 2);
+            }
+
+            #[test]
+            fn test_get_todo_by_fields() {
+                let todo_dir = test_dir("db-get");
+                let first = database_inserte_todo(&todo_dir, test_todo("first")).unwrap();
+                let second = database_inserte_todo(&todo_dir, test_todo("second")).unwrap();
+                let by_id =
+                    database_get_todo(&todo_dir, vec![DatabaseField::Id(second.id.clone())]);
+                let by_two = database_get_todo(
+                    &todo_dir,
+                    vec![
+                        DatabaseField::CreatedBy("alice".to_string()),
+                        DatabaseField::Description("first".to_string()),
+                    ],
+                );
+                let everything = database_get_todo(&todo_dir, Vec::new());
+                let nothing =
+                    database_get_todo(&todo_dir, vec![DatabaseField::Status(TodoState::Done)]);
+                let twice = database_get_todo(
+                    &todo_dir,
+                    vec![
+                        DatabaseField::DueDate(None),
+                        DatabaseField::Id(first.id.clone()),
+                        DatabaseField::DueDate(None),
+                    ],
+                );
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                assert_eq!(by_id, Ok(vec![second.clone()]));
+                assert_eq!(by_two, Ok(vec![first.clone()]));
+                assert_eq!(everything, Ok(vec![first, second]));
+                assert_eq!(
+                    nothing,
+                    Err(vec!["No todo with given fields was found!".to_string()])
+                );
+                assert_eq!(
+                    twice,
+                    Err(vec![
+                        "you can't specify the same field twise! field: due_date".to_string()
+                    ])
+                );
+            }
+
+            #[test]
+            fn test_change_todo_field() {
+                let todo_dir = test_dir("db-field");
+                let todo = database_inserte_todo(&todo_dir, test_todo("old")).unwrap();
+                let due_date = NaiveDate::from_ymd_opt(2024, 5, 1);
+                let changed = database_change_todo_Field(
+                    &todo_dir,
+                    todo.clone(),
+                    vec![
+                        DatabaseField::Description("new".to_string()),
+                        DatabaseField::DueDate(due_date),
+                    ],
+                    "bob".to_string(),
+                )
+                .unwrap();
+                let stored = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                assert_eq!(changed.id, todo.id);
+                assert_eq!(changed.description, "new");
+                assert_eq!(changed.due_date, due_date);
+                assert_eq!(changed.last_changed_by, "bob");
+                assert_eq!(changed.last_change_date, Local::now().date_naive());
+                assert_eq!(stored, vec![changed]);
+            }
+
+            #[test]
+            fn test_change_todo_where() {
+                let todo_dir = test_dir("db-where");
+                let mut todos = Vec::new();
+                for (description, branch) in [("a", "main"), ("b", "fix"), ("c", "main")] {
+                    let mut todo = test_todo(description);
+                    todo.git_branch = Some(branch.to_string());
+                    todos.push(database_inserte_todo(&todo_dir, todo).unwrap());
+                }
+                let renamed = database_change_todo_where(
+                    &todo_dir,
+                    DatabaseField::GitBranch(Some("main".to_string())),
+                    DatabaseField::GitBranch(Some("trunk".to_string())),
+                )
+                .unwrap();
+                let none = database_change_todo_where(
+                    &todo_dir,
+                    DatabaseField::GitBranch(Some("main".to_string())),
+                    DatabaseField::Priority(Some(Priority::A)),
+                )
+                .unwrap();
+                let branches: Vec<Option<String>> = database_get_todos(&todo_dir, TodoState::Open)
+                    .unwrap()
+                    .into_iter()
+                    .map(|todo| todo.git_branch)
+                    .collect();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                assert_eq!(renamed.len(), 2);
+                assert_eq!(renamed[0].id, todos[0].id);
+                assert_eq!(renamed[1].id, todos[2].id);
+                assert!(none.is_empty());
+                assert_eq!(
+                    branches,
+                    ["trunk", "fix", "trunk"].map(|branch| Some(branch.to_string()))
+                );
+            }
+
+            #[test]
+            fn test_delete_todo() {
+                let todo_dir = test_dir("db-delete");
+                let marked = database_inserte_todo(&todo_dir, test_todo("marked")).unwrap();
+                let removed = database_inserte_todo(&todo_dir, test_todo("removed")).unwrap();
+                let marked = database_delete_todo(
+                    &todo_dir,
+                    marked,
+                    DeletionMethod::Mark,
+                    "bob".to_string(),
+                )
+                .unwrap();
+                let removed = database_delete_todo(
+                    &todo_dir,
+                    removed,
+                    DeletionMethod::Delete,
+                    "bob".to_string(),
+                )
+                .unwrap();
+                let all = database_get_all_todos(&todo_dir).unwrap();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                let marked = marked.unwrap();
+                assert_eq!(marked.status, TodoState::Deleted);
+                assert_eq!(marked.last_changed_by, "bob");
+                assert_eq!(removed, None);
+                assert_eq!(all, vec![marked]);
+            }
+
+            #[test]
+            fn test_change_of_missing_todo_fails() {
+                let todo_dir = test_dir("db-missing");
+                let stored = database_inserte_todo(&todo_dir, test_todo("stored")).unwrap();
+                let missing = test_todo("missing");
+                let changed = database_finish_todo(&todo_dir, missing.clone(), "bob".to_string());
+                let changed_all = database_finish_todos(
+                    &todo_dir,
+                    vec![stored.clone(), missing],
+                    "bob".to_string(),
+                );
+                let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+                std::fs::remove_dir_all(&todo_dir).unwrap();
+
+                assert!(changed.is_err());
+                assert!(changed_all.is_err());
+                assert_eq!(open, vec![stored]);
+            }
         }
     }
 }
@@ -0,0 +1,1051 @@
+pub mod export_handler {
+    use chrono::{NaiveDate, NaiveTime, TimeZone};
+    use chrono_tz::Tz;
+    use serde_json::Value;
+
+    use crate::brain::parse_due_date::parse_due_date;
+    use crate::databasehandler::database_handler::todo_database::{
+        generate_id, is_valid_id, tags_in, to_tag, Priority, Todo, TodoState,
+    };
+
+    /// Every field of a `Todo`, in the order they are written.
+    /// CSV columns and Markdown columns use the same names as the JSON keys.
+    pub const FIELDS: [&str; 15] = [
+        "id",
+        "description",
+        "status",
+        "due_date",
+        "due_time",
+        "done_date",
+        "create_date",
+        "created_by",
+        "last_changed_by",
+        "last_change_date",
+        "source",
+        "git_branch",
+        "git_commit",
+        "priority",
+        "tags",
+    ];
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum OutputFormat {
+        Json,
+        Csv,
+        Markdown,
+    }
+    impl OutputFormat {
+        pub fn from_str(format: &str) -> Option<OutputFormat> {
+            match format {
+                "json" => Some(OutputFormat::Json),
+                "csv" => Some(OutputFormat::Csv),
+                "markdown" => Some(OutputFormat::Markdown),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn format_todos(todos: &[Todo], format: &OutputFormat) -> String {
+        match format {
+            OutputFormat::Json => todos_to_json(todos),
+            OutputFormat::Csv => todos_to_csv(todos),
+            OutputFormat::Markdown => todos_to_markdown(todos),
+        }
+    }
+
+    /// A JSON array with one object per todo. Missing dates are `null`.
+    pub fn todos_to_json(todos: &[Todo]) -> String {
+        serde_json::to_string_pretty(todos).expect("todos are serializable")
+    }
+
+    /// Comma separated with a header line. Fields are quoted when needed (RFC 4180).
+    pub fn todos_to_csv(todos: &[Todo]) -> String {
+        let mut lines = vec![FIELDS.join(",")];
+        for todo in todos {
+            let values: Vec<String> = field_values(todo)
+                .into_iter()
+                .map(|value| csv_escape(&value))
+                .collect();
+            lines.push(values.join(","));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// A Markdown table, e.g. for PR descriptions.
+    pub fn todos_to_markdown(todos: &[Todo]) -> String {
+        let mut lines = vec![
+            format!("| {} |", FIELDS.join(" | ")),
+            format!("|{}", "---|".repeat(FIELDS.len())),
+        ];
+        for todo in todos {
+            let values: Vec<String> = field_values(todo)
+                .into_iter()
+                .map(|value| value.replace('|', "\\|").replace(['\n', '\r'], " "))
+                .collect();
+            lines.push(format!("| {} |", values.join(" | ")));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// The values of `FIELDS` written like in the JSON output, missing values are empty.
+    fn field_values(todo: &Todo) -> Vec<String> {
+        let json = serde_json::to_value(todo).expect("todos are serializable");
+        FIELDS
+            .iter()
+            .map(|field| match &json[field] {
+                Value::Null => String::new(),
+                Value::String(value) => value.clone(),
+                Value::Array(values) => values
+                    .iter()
+                    .filter_map(|value| value.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+                value => value.to_string(),
+            })
+            .collect()
+    }
+
+    fn csv_escape(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Formats other todo tools use, for `todo import` and `todo export`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ExchangeFormat {
+        TodoTxt,
+        Ics,
+    }
+    impl ExchangeFormat {
+        pub fn from_str(format: &str) -> Option<ExchangeFormat> {
+            match format {
+                "todotxt" => Some(ExchangeFormat::TodoTxt),
+                "ics" => Some(ExchangeFormat::Ics),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn export_todos(todos: &[Todo], format: &ExchangeFormat) -> String {
+        match format {
+            ExchangeFormat::TodoTxt => todos_to_todotxt(todos),
+            ExchangeFormat::Ics => todos_to_ics(todos),
+        }
+    }
+
+    /// Reads todos written in `format`. Imported todos are created by `created_by`.
+    pub fn import_todos(
+        content: &str,
+        format: &ExchangeFormat,
+        created_by: &str,
+    ) -> Result<Vec<Todo>, String> {
+        match format {
+            ExchangeFormat::TodoTxt => todos_from_todotxt(content, created_by),
+            ExchangeFormat::Ics => todos_from_ics(content, created_by),
+        }
+    }
+
+    const TODOTXT_DATE_FORMAT: &str = "%Y-%m-%d";
+    /// The `key:value` fields written after the description.
+    const TODOTXT_KEYS: [&str; 7] = ["due", "time", "id", "source", "branch", "commit", "pri"];
+
+    /// One todo per line (http://todotxt.org), e.g.
+    /// `x 2024-05-02 2024-04-20 (A) call bob +backend @phone due:2024-05-01 id:xm810w`.
+    ///
+    /// The priority is `(A)` to `(D)`, `+project` and `@context` are part of the description.
+    /// The due time is written as `time:09:00`, the source as `source:src/main.rs:12` and the
+    /// git context as `branch:main commit:1a2b3c...`, finished todos keep their priority as
+    /// `pri:A`. todo.txt has no deleted todos, they are left out.
+    ///
+    /// Description words that look like one of these fields are escaped, `due:friday` is
+    /// written as `due%3Afriday`.
+    pub fn todos_to_todotxt(todos: &[Todo]) -> String {
+        todos
+            .iter()
+            .filter(|todo| todo.status != TodoState::Deleted)
+            .map(|todo| todo_to_todotxt(todo) + "\n")
+            .collect()
+    }
+
+    fn todo_to_todotxt(todo: &Todo) -> String {
+        let (written_priority, description) = split_priority(&todo.description);
+        let priority = todo
+            .priority
+            .and_then(|priority| priority.as_str().chars().next())
+            .or(written_priority);
+        // tags given with --tag aren't in the description
+        let described = tags_in(description);
+        let mut text = vec![description.to_string()];
+        text.extend(
+            todo.tags
+                .iter()
+                .filter(|tag| !described.contains(tag))
+                .cloned(),
+        );
+        let description = text
+            .join(" ")
+            .split(' ')
+            .map(todotxt_escape)
+            .collect::<Vec<String>>()
+            .join(" ");
+        // the dates are always written, so a description starting with `x`, a date or `(A)`
+        // can't be taken for the done marker, a date or the priority
+        let created = todo.create_date.format(TODOTXT_DATE_FORMAT).to_string();
+        let mut parts = Vec::new();
+        if todo.status == TodoState::Done {
+            parts.push("x".to_string());
+            // finishing a todo changes it last
+            let done_date = todo.done_date.unwrap_or(todo.last_change_date);
+            parts.push(done_date.format(TODOTXT_DATE_FORMAT).to_string());
+            parts.push(created);
+            parts.push(description.to_string());
+            if let Some(priority) = priority {
+                parts.push(format!("pri:{}", priority));
+            }
+        } else {
+            if let Some(priority) = priority {
+                parts.push(format!("({})", priority));
+            }
+            parts.push(created);
+            parts.push(description.to_string());
+        }
+        if let Some(due_date) = todo.due_date {
+            parts.push(format!("due:{}", due_date.format(TODOTXT_DATE_FORMAT)));
+        }
+        if let Some(due_time) = todo.due_time {
+            parts.push(format!("time:{}", due_time.format("%H:%M")));
+        }
+        parts.push(format!("id:{}", todo.id));
+        if let Some(source) = &todo.source {
+            parts.push(format!("source:{}", source.replace(' ', "%20")));
+        }
+        if let Some(branch) = &todo.git_branch {
+            parts.push(format!("branch:{}", branch));
+        }
+        if let Some(commit) = &todo.git_commit {
+            parts.push(format!("commit:{}", commit));
+        }
+        parts.join(" ")
+    }
+
+    /// `due:friday` -> `due%3Afriday`, other words stay as they are.
+    fn todotxt_escape(word: &str) -> String {
+        match word.split_once([':', '%']) {
+            Some((key, _)) if TODOTXT_KEYS.contains(&key) => {
+                format!("{}{}", key, uri_escape(&word[key.len()..]))
+            }
+            _ => word.to_string(),
+        }
+    }
+
+    fn todotxt_unescape(word: &str) -> String {
+        match word.split_once('%') {
+            Some((key, rest)) if TODOTXT_KEYS.contains(&key) => {
+                format!("{}{}", key, uri_unescape(&format!("%{}", rest)))
+            }
+            _ => word.to_string(),
+        }
+    }
+
+    /// `(A) call bob` -> (`A`, `call bob`)
+    fn split_priority(description: &str) -> (Option<char>, &str) {
+        let bytes = description.as_bytes();
+        if bytes.len() >= 4
+            && bytes[0] == b'('
+            && bytes[1].is_ascii_uppercase()
+            && bytes[2] == b')'
+            && bytes[3] == b' '
+        {
+            return (Some(bytes[1] as char), &description[4..]);
+        }
+        (None, description)
+    }
+
+    pub fn todos_from_todotxt(content: &str, created_by: &str) -> Result<Vec<Todo>, String> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                todo_from_todotxt(line, created_by)
+                    .map_err(|e| format!("line {}: {}", index + 1, e))
+            })
+            .collect()
+    }
+
+    fn todo_from_todotxt(line: &str, created_by: &str) -> Result<Todo, String> {
+        let mut words = line.split_whitespace().peekable();
+        let parse_date = |word: &str| NaiveDate::parse_from_str(word, TODOTXT_DATE_FORMAT).ok();
+
+        let done = words.next_if_eq(&"x").is_some();
+        let mut priority = None;
+        if !done {
+            if let Some(word) =
+                words.next_if(|word| split_priority(&format!("{} ", word)).0.is_some())
+            {
+                priority = word.chars().nth(1);
+            }
+        }
+        let first_date = words
+            .next_if(|word| parse_date(word).is_some())
+            .and_then(parse_date);
+        // only a finished todo has two dates, for an open one a second is the description
+        let second_date = words
+            .next_if(|word| done && parse_date(word).is_some())
+            .and_then(parse_date);
+        // a finished todo has its done date first, an open todo only has a creation date
+        let (done_date, create_date) = match (done, first_date, second_date) {
+            (true, done_date, Some(create_date)) => (done_date, Some(create_date)),
+            (true, done_date, None) => (done_date, None),
+            (false, create_date, _) => (None, create_date),
+        };
+
+        let mut description_words = Vec::new();
+        let mut due_date = None;
+        let mut due_time = None;
+        let mut id = None;
+        let mut source = None;
+        let mut git_branch = None;
+        let mut git_commit = None;
+        for word in words {
+            match word.split_once(':') {
+                Some(("due", value)) if !value.is_empty() => due_date = Some(to_date(value)?),
+                Some(("time", value)) if !value.is_empty() => {
+                    due_time = Some(
+                        NaiveTime::parse_from_str(value, "%H:%M")
+                            .map_err(|_| format!("couldn't understand time: {}", value))?,
+                    )
+                }
+                Some(("id", value)) if !value.is_empty() => id = Some(value.to_string()),
+                Some(("source", value)) if !value.is_empty() => {
+                    source = Some(value.replace("%20", " "))
+                }
+                Some(("branch", value)) if !value.is_empty() => {
+                    git_branch = Some(value.to_string())
+                }
+                Some(("commit", value)) if !value.is_empty() => {
+                    git_commit = Some(value.to_string())
+                }
+                Some(("pri", value)) if done && value.len() == 1 => priority = value.chars().next(),
+                _ => description_words.push(todotxt_unescape(word)),
+            }
+        }
+        if description_words.is_empty() {
+            return Err("todo has no description".to_string());
+        }
+        let mut description = description_words.join(" ");
+        // todo.txt has priorities up to Z, the ones after D stay in the description
+        let known_priority =
+            priority.and_then(|priority| Priority::from_str(&priority.to_string()));
+        if let (Some(priority), None) = (priority, known_priority) {
+            description = format!("({}) {}", priority, description);
+        }
+
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        let today = chrono::Local::now().naive_local();
+        let mut todo = Todo::new(
+            description,
+            if done {
+                TodoState::Done
+            } else {
+                TodoState::Open
+            },
+            due_date,
+            due_time,
+            create_date.map_or(today, |date| date.and_time(midnight)),
+            created_by.to_string(),
+        );
+        todo.done_date = if done {
+            done_date.or(Some(today.date()))
+        } else {
+            None
+        };
+        if let Some(id) = id {
+            todo.id = imported_id(&id);
+        }
+        todo.source = source;
+        todo.git_branch = git_branch;
+        todo.git_commit = git_commit;
+        todo.priority = known_priority;
+        Ok(todo)
+    }
+
+    /// Ids of other tools can contain anything, they get an id of our own that
+    /// depends only on theirs, so importing the same file again finds the same todos.
+    fn imported_id(id: &str) -> String {
+        if is_valid_id(id) {
+            id.to_string()
+        } else {
+            generate_id(id)
+        }
+    }
+
+    /// `due:` values are usually `2024-05-01`, but everything `-d` accepts works too.
+    fn to_date(value: &str) -> Result<NaiveDate, String> {
+        NaiveDate::parse_from_str(&parse_due_date(value.to_string()), "%d.%m.%Y")
+            .or_else(|_| NaiveDate::parse_from_str(value, TODOTXT_DATE_FORMAT))
+            .map_err(|_| format!("couldn't understand due date: {}", value))
+    }
+
+    const ICS_DATE_FORMAT: &str = "%Y%m%d";
+    const ICS_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+    const ICS_UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+    /// Keep the fields `Todo` has but iCalendar doesn't.
+    const ICS_LAST_CHANGED_BY: &str = "X-TODO-CLI-LAST-CHANGED-BY";
+    const ICS_SOURCE: &str = "X-TODO-CLI-SOURCE";
+    const ICS_GIT_BRANCH: &str = "X-TODO-CLI-GIT-BRANCH";
+    const ICS_GIT_COMMIT: &str = "X-TODO-CLI-GIT-COMMIT";
+
+    /// A VCALENDAR with one VTODO per todo (RFC 5545).
+    ///
+    /// DUE is a date, or a local date-time if the todo has a due time. CREATED, COMPLETED
+    /// and LAST-MODIFIED are UTC. STATUS is NEEDS-ACTION, COMPLETED or CANCELLED, the
+    /// creator is the ORGANIZER. All other fields are kept too, so `todos_from_ics` gives
+    /// back the same todos.
+    pub fn todos_to_ics(todos: &[Todo]) -> String {
+        let stamp = chrono::Utc::now().format(ICS_UTC_FORMAT).to_string();
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//todo-cli//todo-cli//EN".to_string(),
+        ];
+        for todo in todos {
+            lines.push("BEGIN:VTODO".to_string());
+            lines.push(format!("UID:{}", todo.id));
+            lines.push(format!("DTSTAMP:{}", stamp));
+            lines.push(format!("SUMMARY:{}", ics_escape(&todo.description)));
+            let status = match todo.status {
+                TodoState::Open => "NEEDS-ACTION",
+                TodoState::Done => "COMPLETED",
+                TodoState::Deleted => "CANCELLED",
+            };
+            lines.push(format!("STATUS:{}", status));
+            if let Some(priority) = todo.priority {
+                lines.push(format!("PRIORITY:{}", ics_priority(priority)));
+            }
+            if !todo.tags.is_empty() {
+                let categories: Vec<String> = todo.tags.iter().map(|tag| ics_escape(tag)).collect();
+                lines.push(format!("CATEGORIES:{}", categories.join(",")));
+            }
+            match (todo.due_date, todo.due_time) {
+                (Some(date), Some(time)) => lines.push(format!(
+                    "DUE:{}",
+                    date.and_time(time).format(ICS_DATE_TIME_FORMAT)
+                )),
+                (Some(date), None) => {
+                    lines.push(format!("DUE;VALUE=DATE:{}", date.format(ICS_DATE_FORMAT)))
+                }
+                _ => {}
+            }
+            if let Some(done_date) = todo.done_date {
+                lines.push(format!(
+                    "COMPLETED:{}",
+                    ics_utc(done_date.and_time(midnight))
+                ));
+            }
+            lines.push(format!("CREATED:{}", ics_utc(todo.create_date)));
+            lines.push(format!(
+                "LAST-MODIFIED:{}",
+                ics_utc(todo.last_change_date.and_time(midnight))
+            ));
+            lines.push(format!(
+                "ORGANIZER;CN=\"{}\":mailto:{}",
+                ics_parameter_escape(&todo.created_by),
+                uri_escape(&todo.created_by)
+            ));
+            lines.push(format!(
+                "{}:{}",
+                ICS_LAST_CHANGED_BY,
+                ics_escape(&todo.last_changed_by)
+            ));
+            if let Some(source) = &todo.source {
+                lines.push(format!("{}:{}", ICS_SOURCE, ics_escape(source)));
+            }
+            if let Some(branch) = &todo.git_branch {
+                lines.push(format!("{}:{}", ICS_GIT_BRANCH, ics_escape(branch)));
+            }
+            if let Some(commit) = &todo.git_commit {
+                lines.push(format!("{}:{}", ICS_GIT_COMMIT, commit));
+            }
+            lines.push("END:VTODO".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+        lines.iter().map(|line| ics_fold(line) + "\r\n").collect()
+    }
+
+    /// iCalendar priorities go from 1 (highest) to 9 (lowest), 0 is no priority.
+    fn ics_priority(priority: Priority) -> u8 {
+        match priority {
+            Priority::A => 1,
+            Priority::B => 3,
+            Priority::C => 5,
+            Priority::D => 7,
+        }
+    }
+
+    fn priority_from_ics(value: &str) -> Option<Priority> {
+        match value.trim().parse::<u8>().ok()? {
+            1 | 2 => Some(Priority::A),
+            3 | 4 => Some(Priority::B),
+            5 => Some(Priority::C),
+            6..=9 => Some(Priority::D),
+            _ => None,
+        }
+    }
+
+    /// A local date-time in UTC, like `20240420T122200Z`.
+    fn ics_utc(date_time: chrono::NaiveDateTime) -> String {
+        date_time
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map(|local| local.to_utc().naive_utc())
+            // the clocks skipped this time, it can't be local
+            .unwrap_or(date_time)
+            .format(ICS_UTC_FORMAT)
+            .to_string()
+    }
+
+    /// Parameter values are quoted and can't contain `"`, so `^`, `"` and line breaks
+    /// are written as `^^`, `^'` and `^n` (RFC 6868).
+    fn ics_parameter_escape(value: &str) -> String {
+        value
+            .replace('^', "^^")
+            .replace('"', "^'")
+            .replace('\n', "^n")
+    }
+
+    fn ics_parameter_unescape(value: &str) -> String {
+        let mut unescaped = String::new();
+        let mut chars = value.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('^', Some('^')) => unescaped.push('^'),
+                ('^', Some('\'')) => unescaped.push('"'),
+                ('^', Some('n')) => unescaped.push('\n'),
+                _ => {
+                    unescaped.push(c);
+                    continue;
+                }
+            }
+            chars.next();
+        }
+        unescaped
+    }
+
+    /// Percent-encodes everything but letters, digits and `-._~@`, e.g. for `mailto:`.
+    fn uri_escape(value: &str) -> String {
+        value
+            .bytes()
+            .map(|byte| match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{:02X}", byte),
+            })
+            .collect()
+    }
+
+    fn uri_unescape(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut unescaped = Vec::new();
+        let mut index = 0;
+        while index < bytes.len() {
+            let hex = value
+                .get(index + 1..index + 3)
+                .filter(|_| bytes[index] == b'%')
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match hex {
+                Some(byte) => {
+                    unescaped.push(byte);
+                    index += 3;
+                }
+                None => {
+                    unescaped.push(bytes[index]);
+                    index += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&unescaped).to_string()
+    }
+
+    /// A line break is written as `\n`, also a `\r\n` or `\r` one.
+    fn ics_escape(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .replace('\n', "\\n")
+    }
+
+    /// `a,b\,c` -> `a`, `b,c`
+    fn split_ics_list(value: &str) -> Vec<String> {
+        let mut items = vec![String::new()];
+        let mut escaped = false;
+        for c in value.chars() {
+            match c {
+                ',' if !escaped => items.push(String::new()),
+                c => items.last_mut().expect("starts with one item").push(c),
+            }
+            escaped = c == '\\' && !escaped;
+        }
+        items
+            .iter()
+            .map(|item| ics_unescape(item.trim()))
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    fn ics_unescape(text: &str) -> String {
+        let mut unescaped = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            }
+        }
+        unescaped
+    }
+
+    /// Lines longer than 75 bytes continue on the next line after a space.
+    fn ics_fold(line: &str) -> String {
+        let mut folded = String::new();
+        let mut length = 0;
+        for c in line.chars() {
+            if length + c.len_utf8() > 75 {
+                folded.push_str("\r\n ");
+                length = 1;
+            }
+            folded.push(c);
+            length += c.len_utf8();
+        }
+        folded
+    }
+
+    /// Reads every VTODO of an iCalendar file. Todos without ORGANIZER are created by `created_by`.
+    pub fn todos_from_ics(content: &str, created_by: &str) -> Result<Vec<Todo>, String> {
+        // unfold: a line starting with a space or tab continues the line before
+        let mut lines: Vec<String> = Vec::new();
+        for line in content.lines() {
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(rest), Some(last)) => last.push_str(rest),
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        let mut todos = Vec::new();
+        let mut properties: Option<Vec<(String, String, String)>> = None;
+        for line in lines {
+            let (name, parameters, value) = split_ics_line(&line);
+            match (name.as_str(), value.as_str(), properties.as_mut()) {
+                ("BEGIN", "VTODO", _) => properties = Some(Vec::new()),
+                ("END", "VTODO", Some(_)) => {
+                    let vtodo = properties.take().unwrap();
+                    todos.push(todo_from_vtodo(&vtodo, created_by)?);
+                }
+                (_, _, Some(vtodo)) => vtodo.push((name, parameters, value)),
+                _ => {}
+            }
+        }
+        Ok(todos)
+    }
+
+    /// `DUE;VALUE=DATE:20240501` -> (`DUE`, `VALUE=DATE`, `20240501`)
+    fn split_ics_line(line: &str) -> (String, String, String) {
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(index, c)| {
+            match c {
+                '"' => quoted = !quoted,
+                ':' if !quoted => return Some(index),
+                _ => {}
+            }
+            None
+        });
+        let (head, value) = match colon {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => (line, ""),
+        };
+        // a parameter value can contain `;` if it is quoted, the name can't
+        let (name, parameters) = head.split_once(';').unwrap_or((head, ""));
+        (
+            name.to_uppercase(),
+            parameters.to_string(),
+            value.to_string(),
+        )
+    }
+
+    /// `CN="Doe; Jane";ROLE=CHAIR` -> `CN="Doe; Jane"`, `ROLE=CHAIR`
+    fn split_ics_parameters(parameters: &str) -> Vec<&str> {
+        let mut split = Vec::new();
+        let mut quoted = false;
+        let mut start = 0;
+        for (index, c) in parameters.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => {
+                    split.push(&parameters[start..index]);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        split.push(&parameters[start..]);
+        split
+    }
+
+    fn todo_from_vtodo(
+        properties: &[(String, String, String)],
+        created_by: &str,
+    ) -> Result<Todo, String> {
+        let property = |wanted: &str| {
+            properties
+                .iter()
+                .find(|(name, _, _)| name == wanted)
+                .map(|(_, parameters, value)| (parameters.as_str(), value.as_str()))
+        };
+
+        let description = property("SUMMARY")
+            .map(|(_, value)| ics_unescape(value))
+            .ok_or("VTODO without SUMMARY")?;
+        let status = match property("STATUS").map(|(_, value)| value.to_uppercase()) {
+            Some(status) if status == "COMPLETED" => TodoState::Done,
+            Some(status) if status == "CANCELLED" => TodoState::Deleted,
+            _ => TodoState::Open,
+        };
+        let (due_date, due_time) = match property("DUE") {
+            Some((parameters, value)) => match parse_ics_date_time(parameters, value)? {
+                (date, Some(time)) => (Some(date), Some(time)),
+                (date, None) => (Some(date), None),
+            },
+            None => (None, None),
+        };
+        let create_date = match property("CREATED") {
+            Some((parameters, value)) => {
+                let (date, time) = parse_ics_date_time(parameters, value)?;
+                date.and_time(time.unwrap_or_default())
+            }
+            None => chrono::Local::now().naive_local(),
+        };
+        let organizer = property("ORGANIZER").map(|(parameters, value)| {
+            split_ics_parameters(parameters)
+                .iter()
+                .find_map(|parameter| parameter.strip_prefix("CN="))
+                .map(|name| ics_parameter_unescape(name.trim_matches('"')))
+                .unwrap_or_else(|| uri_unescape(value.trim_start_matches("mailto:")))
+        });
+
+        let mut todo = Todo::new(
+            description,
+            status,
+            due_date,
+            due_time,
+            create_date,
+            organizer.unwrap_or_else(|| created_by.to_string()),
+        );
+        // UIDs of other apps are often long and can contain spaces
+        if let Some((_, uid)) = property("UID") {
+            todo.id = imported_id(uid);
+        }
+        if let Some((parameters, value)) = property("COMPLETED") {
+            todo.done_date = Some(parse_ics_date_time(parameters, value)?.0);
+        } else if todo.status == TodoState::Done {
+            todo.done_date = Some(chrono::Local::now().date_naive());
+        }
+        if let Some((parameters, value)) = property("LAST-MODIFIED") {
+            todo.last_change_date = parse_ics_date_time(parameters, value)?.0;
+        }
+        if let Some((_, value)) = property(ICS_LAST_CHANGED_BY) {
+            todo.last_changed_by = ics_unescape(value);
+        }
+        todo.source = property(ICS_SOURCE).map(|(_, value)| ics_unescape(value));
+        todo.git_branch = property(ICS_GIT_BRANCH).map(|(_, value)| ics_unescape(value));
+        todo.git_commit = property(ICS_GIT_COMMIT).map(|(_, value)| value.to_string());
+        todo.priority = property("PRIORITY").and_then(|(_, value)| priority_from_ics(value));
+        if let Some((_, value)) = property("CATEGORIES") {
+            // categories of other apps are plain words, sometimes several
+            for tag in split_ics_list(value)
+                .iter()
+                .filter_map(|category| to_tag(category))
+            {
+                if !todo.tags.contains(&tag) {
+                    todo.tags.push(tag);
+                }
+            }
+        }
+        Ok(todo)
+    }
+
+    /// `20240501`, `20240501T093000` or `20240501T073000Z`. UTC and times with a `TZID`
+    /// parameter are turned into local time.
+    fn parse_ics_date_time(
+        parameters: &str,
+        value: &str,
+    ) -> Result<(NaiveDate, Option<NaiveTime>), String> {
+        if let Ok(date) = NaiveDate::parse_from_str(value, ICS_DATE_FORMAT) {
+            return Ok((date, None));
+        }
+        let zone = split_ics_parameters(parameters)
+            .iter()
+            .find_map(|parameter| parameter.strip_prefix("TZID="))
+            // zones chrono-tz doesn't know, like the Windows names of Outlook, stay as they are
+            .and_then(|zone| zone.trim_matches('"').parse::<Tz>().ok());
+        let date_time = match value.strip_suffix('Z') {
+            Some(utc) => {
+                chrono::NaiveDateTime::parse_from_str(utc, ICS_DATE_TIME_FORMAT).map(|date_time| {
+                    date_time
+                        .and_utc()
+                        .with_timezone(&chrono::Local)
+                        .naive_local()
+                })
+            }
+            None => {
+                chrono::NaiveDateTime::parse_from_str(value, ICS_DATE_TIME_FORMAT).map(
+                    |date_time| {
+                        // a time skipped by a daylight saving change stays as it is
+                        zone.and_then(|zone| zone.from_local_datetime(&date_time).earliest())
+                            .map_or(date_time, |zoned| {
+                                zoned.with_timezone(&chrono::Local).naive_local()
+                            })
+                    },
+                )
+            }
+        }
+        .map_err(|_| format!("couldn't understand date: {}", value))?;
+        Ok((date_time.date(), Some(date_time.time())))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::fixed_todo;
+
+        /// Needs quoting in csv and escaping in markdown.
+        const QUOTED: &str = "say \"hi\", then | leave";
+
+        #[test]
+        fn test_todotxt_round_trip() {
+            let open = fixed_todo(QUOTED);
+            let mut prioritized = fixed_todo(QUOTED);
+            prioritized.id = "prio01".to_string();
+            prioritized.description = "call bob +backend @phone".to_string();
+            prioritized.priority = Some(Priority::B);
+            prioritized.tags = vec![
+                "+backend".to_string(),
+                "@phone".to_string(),
+                "+urgent".to_string(),
+            ];
+            prioritized.due_time = NaiveTime::from_hms_opt(9, 30, 0);
+            prioritized.status = TodoState::Done;
+            prioritized.done_date = NaiveDate::from_ymd_opt(2024, 5, 2);
+            prioritized.source = Some("my notes.md:3".to_string());
+            prioritized.git_branch = Some("feature/login".to_string());
+            prioritized.git_commit = Some("1a2b3c".to_string());
+
+            let exported = todos_to_todotxt(&[open.clone(), prioritized.clone()]);
+            let imported = todos_from_todotxt(&exported, "bob").unwrap();
+
+            assert_eq!(
+                exported.lines().nth(1),
+                Some("x 2024-05-02 2024-04-20 call bob +backend @phone +urgent pri:B due:2024-05-01 time:09:30 id:prio01 source:my%20notes.md:3 branch:feature/login commit:1a2b3c")
+            );
+            // tags given with --tag end up in the description
+            prioritized.description.push_str(" +urgent");
+            for (imported, original) in imported.iter().zip([open, prioritized]) {
+                assert_eq!(imported.id, original.id);
+                assert_eq!(imported.description, original.description);
+                assert_eq!(imported.priority, original.priority);
+                assert_eq!(imported.tags, original.tags);
+                assert_eq!(imported.status, original.status);
+                assert_eq!(imported.due_date, original.due_date);
+                assert_eq!(imported.due_time, original.due_time);
+                assert_eq!(imported.done_date, original.done_date);
+                assert_eq!(imported.create_date.date(), original.create_date.date());
+                assert_eq!(imported.source, original.source);
+                assert_eq!(imported.git_branch, original.git_branch);
+                assert_eq!(imported.git_commit, original.git_commit);
+            }
+        }
+
+        #[test]
+        fn test_todotxt_round_trip_of_special_descriptions() {
+            let descriptions = [
+                "x marks the spot",
+                "2024-05-01 meeting",
+                "move due:friday to id:abc123 or due%3Asaturday",
+                "read http://example.com/a:b",
+            ];
+            let mut todos: Vec<Todo> = descriptions.iter().map(|d| fixed_todo(d)).collect();
+            todos[1].status = TodoState::Done;
+            todos[1].done_date = None;
+
+            let exported = todos_to_todotxt(&todos);
+            let imported = todos_from_todotxt(&exported, "bob").unwrap();
+
+            assert_eq!(
+                exported.lines().nth(2),
+                Some("2024-04-20 move due%3Afriday to id%3Aabc123 or due%253Asaturday due:2024-05-01 id:abc123")
+            );
+            for (imported, original) in imported.iter().zip(&todos) {
+                assert_eq!(imported.description, original.description);
+                assert_eq!(imported.status, original.status);
+                assert_eq!(imported.id, original.id);
+                assert_eq!(imported.due_date, original.due_date);
+                assert_eq!(imported.create_date.date(), original.create_date.date());
+            }
+            assert_eq!(imported[1].done_date, Some(todos[1].last_change_date));
+        }
+
+        #[test]
+        fn test_todotxt_import() {
+            let content =
+                "(A) 2024-01-03 pay rent due:tomorrow\n\nx buy milk http://shop.example\n(F) later\n";
+            let todos = todos_from_todotxt(content, "alice").unwrap();
+            let tomorrow = chrono::Local::now().date_naive() + chrono::Duration::days(1);
+
+            assert_eq!(todos.len(), 3);
+            assert_eq!(todos[0].description, "pay rent");
+            assert_eq!(todos[0].priority, Some(Priority::A));
+            assert_eq!(todos[0].due_date, Some(tomorrow));
+            assert_eq!(
+                todos[0].create_date.date(),
+                NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()
+            );
+            assert_eq!(todos[1].status, TodoState::Done);
+            assert_eq!(todos[1].description, "buy milk http://shop.example");
+            assert_eq!(todos[2].description, "(F) later");
+            assert_eq!(todos[2].priority, None);
+            assert!(todos_from_todotxt("x 2024-01-03", "alice").is_err());
+
+            let ids =
+                todos_from_todotxt("a id:abc123\nb id:123456\nc id:Some-Id\n", "alice").unwrap();
+            assert_eq!(ids[0].id, "abc123");
+            assert_eq!(ids[1].id, generate_id("123456"));
+            assert_eq!(ids[2].id, generate_id("Some-Id"));
+            assert!(todos_from_todotxt("a due:someday", "alice").is_err());
+        }
+
+        #[test]
+        fn test_ics_round_trip() {
+            let open = fixed_todo(QUOTED);
+            let mut done = fixed_todo(QUOTED);
+            done.id = "done01".to_string();
+            done.description = "a long description; with, special\\characters\nand a second line that needs folding".to_string();
+            done.due_time = NaiveTime::from_hms_opt(9, 30, 0);
+            done.status = TodoState::Done;
+            done.done_date = NaiveDate::from_ymd_opt(2024, 5, 2);
+            done.last_changed_by = "bob".to_string();
+            done.created_by = "Doe; \"JD\" ^ Jane".to_string();
+            done.source = Some("src/main.rs:12".to_string());
+            done.git_branch = Some("fix,things".to_string());
+            done.git_commit = Some("1a2b3c".to_string());
+            done.priority = Some(Priority::C);
+            done.tags = vec!["+back,end".to_string(), "@bob".to_string()];
+            let mut deleted = fixed_todo(QUOTED);
+            deleted.id = "dele01".to_string();
+            deleted.status = TodoState::Deleted;
+            deleted.due_date = None;
+            let todos = vec![open, done, deleted];
+
+            let exported = todos_to_ics(&todos);
+            let imported = todos_from_ics(&exported, "someone").unwrap();
+
+            assert!(exported.contains("DUE;VALUE=DATE:20240501\r\n"));
+            assert!(exported.contains("DUE:20240501T093000\r\n"));
+            assert!(exported.contains("STATUS:CANCELLED\r\n"));
+            assert!(exported.contains("PRIORITY:5\r\n"));
+            assert!(exported.contains("CATEGORIES:+back\\,end,@bob\r\n"));
+            assert!(exported.contains(
+                "ORGANIZER;CN=\"Doe; ^'JD^' ^^ Jane\":mailto:Doe%3B%20%22JD%22%20%5E%20Jane\r\n"
+            ));
+            for property in ["CREATED:", "COMPLETED:", "LAST-MODIFIED:"] {
+                assert!(exported
+                    .lines()
+                    .filter(|line| line.starts_with(property))
+                    .all(|line| line.len() == property.len() + 16 && line.ends_with('Z')));
+            }
+            assert!(exported.lines().all(|line| line.len() <= 76));
+            assert_eq!(imported, todos);
+        }
+
+        #[test]
+        fn test_ics_escapes_every_line_break() {
+            let mut todo = fixed_todo("first\r\nsecond\rthird");
+            todo.source = Some("notes\r.md:1".to_string());
+
+            let exported = todos_to_ics(&[todo]);
+            let imported = todos_from_ics(&exported, "bob").unwrap();
+
+            assert!(exported.split("\r\n").all(|line| !line.contains('\r')));
+            assert_eq!(imported[0].description, "first\nsecond\nthird");
+            assert_eq!(imported[0].source.as_deref(), Some("notes\n.md:1"));
+        }
+
+        #[test]
+        fn test_ics_import_from_other_apps() {
+            let content = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:not a todo\nEND:VEVENT\n\
+                           BEGIN:VTODO\nUID:1234-abcd\nSUMMARY:call\n  bob\nDUE;TZID=Europe/Berlin:20240501T120000\n\
+                           ORGANIZER:mailto:alice@example.com\nCATEGORIES:Work,@home,Side Project\nEND:VTODO\nEND:VCALENDAR\n";
+            let todos = todos_from_ics(content, "bob").unwrap();
+
+            assert_eq!(todos.len(), 1);
+            assert_eq!(todos[0].id, generate_id("1234-abcd"));
+            assert_eq!(todos[0].description, "call bob");
+            // 12:00 in Berlin during daylight saving time is 10:00 UTC
+            let due = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+                .unwrap()
+                .and_utc()
+                .with_timezone(&chrono::Local)
+                .naive_local();
+            assert_eq!(todos[0].due_date, Some(due.date()));
+            assert_eq!(todos[0].due_time, Some(due.time()));
+            assert_eq!(todos[0].created_by, "alice@example.com");
+            assert_eq!(todos[0].tags, vec!["+Work", "@home", "+Side-Project"]);
+            assert!(todos_from_ics("BEGIN:VTODO\nUID:x\nEND:VTODO\n", "bob").is_err());
+            let unknown_zone = todos_from_ics(
+                "BEGIN:VTODO\nSUMMARY:call\nDUE;TZID=\"W. Europe Standard Time\":20240501T120000\nEND:VTODO\n",
+                "bob",
+            )
+            .unwrap();
+            assert_eq!(unknown_zone[0].due_time, NaiveTime::from_hms_opt(12, 0, 0));
+        }
+
+        #[test]
+        fn test_json_has_every_field() {
+            let json: Value = serde_json::from_str(&todos_to_json(&[fixed_todo(QUOTED)])).unwrap();
+            let object = json[0].as_object().unwrap();
+            assert_eq!(object.len(), FIELDS.len());
+            for field in FIELDS {
+                assert!(object.contains_key(field), "{}", field);
+            }
+            assert_eq!(json[0]["due_date"], "2024-05-01");
+            assert_eq!(json[0]["due_time"], Value::Null);
+        }
+
+        #[test]
+        fn test_csv_and_markdown() {
+            let csv = todos_to_csv(&[fixed_todo(QUOTED)]);
+            let markdown = todos_to_markdown(&[fixed_todo(QUOTED)]);
+            assert_eq!(
+                csv,
+                "id,description,status,due_date,due_time,done_date,create_date,created_by,last_changed_by,last_change_date,source,git_branch,git_commit,priority,tags\n\
+                 abc123,\"say \"\"hi\"\", then | leave\",open,2024-05-01,,,2024-04-20T14:22:00,alice,alice,2024-04-21,,,,,\n"
+            );
+            assert_eq!(
+                markdown.lines().nth(2),
+                Some("| abc123 | say \"hi\", then \\| leave | open | 2024-05-01 |  |  | 2024-04-20T14:22:00 | alice | alice | 2024-04-21 |  |  |  |  |  |")
+            );
+        }
+    }
+}
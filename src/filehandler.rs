@@ -1,26 +1,96 @@
 pub mod file_handler {
     use std::{
-        fs::{self, OpenOptions},
+        fs::{self, File, OpenOptions},
         io::{BufRead, BufReader, Write},
-        path::PathBuf,
+        path::{Path, PathBuf},
+        process,
     };
 
-    pub fn append_line(file: &PathBuf, line: String) -> Result<(), String> {
-        let mut oo_file = match OpenOptions::new()
-            .read(false)
-            .write(false)
-            .append(true)
-            .create(false)
-            .open(file)
-        {
-            Ok(file) => file,
-            Err(e) => panic!("failed to open file! {}", e),
+    /// Name of the file that is locked while a command works on a todo directory.
+    pub const LOCK_FILE_NAME: &str = ".todo.lock";
+
+    /// Takes an advisory lock on `dir`, waiting until no other `todo` holds it.
+    ///
+    /// The lock is released when the returned file is dropped.
+    pub fn lock_dir(dir: &Path) -> Result<File, String> {
+        let mut lock_path = dir.to_path_buf();
+        lock_path.push(LOCK_FILE_NAME);
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| format!("Failed to open the lock file: {}", e))?;
+        lock_file
+            .lock()
+            .map_err(|e| format!("Failed to lock the todo directory: {}", e))?;
+        Ok(lock_file)
+    }
+
+    /// Adds every name of `names` to the `.gitignore` in `dir` that isn't in it yet.
+    /// Returns the added names.
+    pub fn add_to_gitignore(dir: &Path, names: &[&str]) -> Result<Vec<String>, String> {
+        let mut gitignore_path = dir.to_path_buf();
+        gitignore_path.push(".gitignore");
+        let mut lines = if gitignore_path.exists() {
+            read_lines(&gitignore_path)?
+        } else {
+            Vec::new()
         };
+        let mut added = Vec::new();
+        for name in names {
+            if !lines.iter().any(|line| line.trim() == *name) {
+                lines.push(name.to_string());
+                added.push(name.to_string());
+            }
+        }
+        if !added.is_empty() {
+            write_lines(&gitignore_path, lines)?;
+        }
+        Ok(added)
+    }
 
-        if let Err(e) = writeln!(oo_file, "{}", line) {
-            eprintln!("Coldn't write to file: {}", e);
+    pub fn append_line(file: &PathBuf, line: String) -> Result<(), String> {
+        let mut lines = read_lines(file)?;
+        lines.push(line);
+        write_lines(file, lines)
+    }
+
+    pub fn read_lines(file: &PathBuf) -> Result<Vec<String>, String> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", file.to_string_lossy(), e))?;
+        Ok(content.lines().map(|line| line.to_string()).collect())
+    }
+
+    /// Writes `lines` into a temporary file next to `file` and renames it over `file`,
+    /// so `file` is either completely old or completely new, even if the process is killed.
+    /// The new file gets the permissions of the old one.
+    pub fn write_lines(file: &PathBuf, lines: Vec<String>) -> Result<(), String> {
+        let file_name = file
+            .file_name()
+            .ok_or_else(|| format!("Not a file: {}", file.to_string_lossy()))?
+            .to_string_lossy();
+        let mut temp_path = file.clone();
+        temp_path.set_file_name(format!("{}.tmp-{}", file_name, process::id()));
+
+        let mut output = File::create(&temp_path)
+            .map_err(|e| format!("Failed to open the file for writing: {}", e))?;
+        if let Ok(metadata) = fs::metadata(file) {
+            output
+                .set_permissions(metadata.permissions())
+                .map_err(|e| format!("Failed to copy the permissions: {}", e))?;
         }
-        Ok(())
+        for line in lines {
+            writeln!(output, "{}", line).map_err(|e| format!("Failed to write to file: {}", e))?;
+        }
+        output
+            .sync_all()
+            .map_err(|e| format!("Failed to write to file: {}", e))?;
+
+        fs::rename(&temp_path, file).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            format!("Failed to replace {}: {}", file.to_string_lossy(), e)
+        })
     }
 
     // I got this staight from GPT4
@@ -42,12 +112,75 @@ pub mod file_handler {
         }
 
         // Write back to the file
-        let mut output = fs::File::create(file)
-            .map_err(|e| format!("Failed to open the file for writing: {}", e))?;
-        for line in lines {
-            writeln!(output, "{}", line).map_err(|e| format!("Failed to write to file: {}", e))?;
+        write_lines(file, lines)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::test_dir;
+
+        #[test]
+        fn test_write_lines_replaces_file_without_leftovers() {
+            let dir = test_dir("file-write");
+            let file = dir.join(".todo.todo");
+            fs::write(&file, "old\n").unwrap();
+
+            write_lines(&file, vec!["a".to_string(), "b".to_string()]).unwrap();
+            append_line(&file, "c".to_string()).unwrap();
+            remove_line(&file, 0).unwrap();
+            let content = fs::read_to_string(&file).unwrap();
+            let entries = fs::read_dir(&dir).unwrap().count();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(content, "b\nc\n");
+            assert_eq!(entries, 1);
         }
 
-        Ok(())
+        #[cfg(unix)]
+        #[test]
+        fn test_write_lines_keeps_permissions() {
+            use std::os::unix::fs::PermissionsExt;
+            let dir = test_dir("file-mode");
+            let file = dir.join(".todo.todo");
+            fs::write(&file, "old\n").unwrap();
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+
+            write_lines(&file, vec!["new".to_string()]).unwrap();
+            let mode = fs::metadata(&file).unwrap().permissions().mode();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        #[test]
+        fn test_add_to_gitignore() {
+            let dir = test_dir("file-gitignore");
+            fs::write(dir.join(".gitignore"), "target\n.todo.lock\n").unwrap();
+
+            let added = add_to_gitignore(&dir, &[LOCK_FILE_NAME, ".todo.journal"]).unwrap();
+            let again = add_to_gitignore(&dir, &[LOCK_FILE_NAME, ".todo.journal"]).unwrap();
+            let content = fs::read_to_string(dir.join(".gitignore")).unwrap();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(added, vec![".todo.journal"]);
+            assert!(again.is_empty());
+            assert_eq!(content, "target\n.todo.lock\n.todo.journal\n");
+        }
+
+        #[test]
+        fn test_lock_dir_is_released_on_drop() {
+            let dir = test_dir("file-lock");
+
+            let lock = lock_dir(&dir).unwrap();
+            let mut lock_path = dir.clone();
+            lock_path.push(LOCK_FILE_NAME);
+            let other = File::open(&lock_path).unwrap();
+            assert!(other.try_lock().is_err());
+            drop(lock);
+            assert!(other.try_lock().is_ok());
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
     }
 }
@@ -0,0 +1,522 @@
+pub mod filter_handler {
+    use std::cmp::Ordering;
+
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    use crate::brain::parse_due_date::parse_due_date;
+    use crate::databasehandler::database_handler::todo_database::{Priority, Todo, TodoState};
+
+    /// A parsed filter expression like `due<fri and not by:alice`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Filter {
+        And(Box<Filter>, Box<Filter>),
+        Or(Box<Filter>, Box<Filter>),
+        Not(Box<Filter>),
+        Term(Term),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Term {
+        /// `None` is written as `none` and means the todo has no such date.
+        Date(DateField, Comparison, Option<NaiveDate>),
+        Text(TextField, Comparison, String),
+        Status(TodoState),
+        /// `None` is written as `none` and means the todo has no priority.
+        Priority(Option<Priority>),
+        /// `+backend` or `@alice`, ignoring case.
+        Tag(String),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum DateField {
+        Due,
+        Created,
+        Done,
+        Changed,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum TextField {
+        Description,
+        CreatedBy,
+        ChangedBy,
+        Id,
+        Branch,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Comparison {
+        Equal,
+        Less,
+        LessOrEqual,
+        Greater,
+        GreaterOrEqual,
+        Contains,
+    }
+    impl Comparison {
+        // longest first, so `<=` isn't read as `<`
+        const OPERATORS: [(&'static str, Comparison); 6] = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            (":", Comparison::Equal),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("~", Comparison::Contains),
+        ];
+    }
+
+    impl Filter {
+        pub fn matches(&self, todo: &Todo) -> bool {
+            match self {
+                Filter::And(left, right) => left.matches(todo) && right.matches(todo),
+                Filter::Or(left, right) => left.matches(todo) || right.matches(todo),
+                Filter::Not(filter) => !filter.matches(todo),
+                Filter::Term(term) => term.matches(todo),
+            }
+        }
+
+        /// Without a `status` term only open todos are listed.
+        pub fn mentions_status(&self) -> bool {
+            match self {
+                Filter::And(left, right) | Filter::Or(left, right) => {
+                    left.mentions_status() || right.mentions_status()
+                }
+                Filter::Not(filter) => filter.mentions_status(),
+                Filter::Term(term) => matches!(term, Term::Status(_)),
+            }
+        }
+    }
+
+    impl Term {
+        fn matches(&self, todo: &Todo) -> bool {
+            match self {
+                Term::Date(field, comparison, value) => {
+                    let date = match field {
+                        DateField::Due => todo.due_date,
+                        DateField::Created => Some(todo.create_date.date()),
+                        DateField::Done => todo.done_date,
+                        DateField::Changed => Some(todo.last_change_date),
+                    };
+                    match (date, value) {
+                        (Some(date), Some(value)) => match comparison {
+                            Comparison::Less => date < *value,
+                            Comparison::LessOrEqual => date <= *value,
+                            Comparison::Greater => date > *value,
+                            Comparison::GreaterOrEqual => date >= *value,
+                            _ => date == *value,
+                        },
+                        (None, None) => true,
+                        _ => false,
+                    }
+                }
+                Term::Text(field, comparison, value) => {
+                    let text = match field {
+                        TextField::Description => &todo.description,
+                        TextField::CreatedBy => &todo.created_by,
+                        TextField::ChangedBy => &todo.last_changed_by,
+                        TextField::Id => &todo.id,
+                        TextField::Branch => todo.git_branch.as_deref().unwrap_or_default(),
+                    };
+                    match comparison {
+                        Comparison::Contains => text.to_lowercase().contains(&value.to_lowercase()),
+                        _ => text == value,
+                    }
+                }
+                Term::Status(status) => &todo.status == status,
+                Term::Priority(priority) => &todo.priority == priority,
+                Term::Tag(tag) => todo.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            }
+        }
+    }
+
+    /// Parses a filter like `due:today`, `due<fri`, `by:alice`, `status:open` or `text~deploy`.
+    /// Terms are combined with `and`, `or`, `not` and parentheses. Terms next to each other
+    /// are combined with `and`. Values with spaces are quoted: `due<"in 3 days"`.
+    pub fn parse_filter(filter: &str) -> Result<Filter, String> {
+        let tokens = tokenize(filter)?;
+        if tokens.is_empty() {
+            return Err("empty filter".to_string());
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let parsed = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(parsed),
+            Some(token) => Err(format!("unexpected `{}` in filter", token)),
+        }
+    }
+
+    /// Splits at whitespace and parentheses. Quotes group words and are removed.
+    fn tokenize(filter: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Vec::new();
+        let mut token = String::new();
+        let mut quoted = false;
+        for c in filter.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                c if quoted => token.push(c),
+                '(' | ')' => {
+                    if !token.is_empty() {
+                        tokens.push(std::mem::take(&mut token));
+                    }
+                    tokens.push(c.to_string());
+                }
+                c if c.is_whitespace() => {
+                    if !token.is_empty() {
+                        tokens.push(std::mem::take(&mut token));
+                    }
+                }
+                c => token.push(c),
+            }
+        }
+        if quoted {
+            return Err("missing closing quote in filter".to_string());
+        }
+        if !token.is_empty() {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    struct Parser {
+        tokens: Vec<String>,
+        position: usize,
+    }
+    impl Parser {
+        fn peek(&self) -> Option<&str> {
+            self.tokens.get(self.position).map(|token| token.as_str())
+        }
+
+        fn next(&mut self) -> Option<String> {
+            let token = self.tokens.get(self.position).cloned();
+            self.position += 1;
+            token
+        }
+
+        fn parse_or(&mut self) -> Result<Filter, String> {
+            let mut filter = self.parse_and()?;
+            while self.peek() == Some("or") {
+                self.next();
+                filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+            }
+            Ok(filter)
+        }
+
+        fn parse_and(&mut self) -> Result<Filter, String> {
+            let mut filter = self.parse_not()?;
+            loop {
+                match self.peek() {
+                    Some("and") => {
+                        self.next();
+                    }
+                    None | Some("or") | Some(")") => return Ok(filter),
+                    _ => {}
+                }
+                filter = Filter::And(Box::new(filter), Box::new(self.parse_not()?));
+            }
+        }
+
+        fn parse_not(&mut self) -> Result<Filter, String> {
+            if self.peek() == Some("not") {
+                self.next();
+                return Ok(Filter::Not(Box::new(self.parse_not()?)));
+            }
+            match self.next() {
+                Some(token) if token == "(" => {
+                    let filter = self.parse_or()?;
+                    match self.next() {
+                        Some(token) if token == ")" => Ok(filter),
+                        _ => Err("missing `)` in filter".to_string()),
+                    }
+                }
+                Some(token) => parse_term(&token).map(Filter::Term),
+                None => Err("filter ends too early".to_string()),
+            }
+        }
+    }
+
+    fn parse_term(term: &str) -> Result<Term, String> {
+        if term.len() > 1 && term.starts_with(['+', '@']) {
+            return Ok(Term::Tag(term.to_string()));
+        }
+        let (position, operator, comparison) = Comparison::OPERATORS
+            .iter()
+            .filter_map(|(operator, comparison)| {
+                term.find(operator)
+                    .map(|position| (position, *operator, comparison.clone()))
+            })
+            // the first operator in the term, `<=` wins over `<` at the same position
+            .min_by_key(|(position, operator, _)| (*position, usize::MAX - operator.len()))
+            .ok_or_else(|| format!("`{}` is not a filter term like due:today", term))?;
+        let field = &term[..position];
+        let value = &term[position + operator.len()..];
+        if value.is_empty() {
+            return Err(format!("`{}` has no value", term));
+        }
+
+        let date_field = match field {
+            "due" => Some(DateField::Due),
+            "created" => Some(DateField::Created),
+            "done" => Some(DateField::Done),
+            "changed" => Some(DateField::Changed),
+            _ => None,
+        };
+        if let Some(date_field) = date_field {
+            if comparison == Comparison::Contains {
+                return Err(format!("`~` doesn't work with dates: {}", term));
+            }
+            if value == "none" {
+                if comparison != Comparison::Equal {
+                    return Err(format!("`none` only works with `:`: {}", term));
+                }
+                return Ok(Term::Date(date_field, comparison, None));
+            }
+            return Ok(Term::Date(date_field, comparison, Some(parse_date(value)?)));
+        }
+
+        let text_field = match field {
+            "text" => TextField::Description,
+            "by" => TextField::CreatedBy,
+            "changedby" => TextField::ChangedBy,
+            "id" => TextField::Id,
+            "branch" => TextField::Branch,
+            "status" => {
+                if comparison != Comparison::Equal {
+                    return Err(format!("status only works with `:`: {}", term));
+                }
+                return TodoState::from_str(value)
+                    .map(Term::Status)
+                    .ok_or_else(|| format!("unknown status {}, use open/done/deleted", value));
+            }
+            "priority" | "pri" => {
+                if comparison != Comparison::Equal {
+                    return Err(format!("priority only works with `:`: {}", term));
+                }
+                if value == "none" {
+                    return Ok(Term::Priority(None));
+                }
+                return Priority::from_str(value)
+                    .map(|priority| Term::Priority(Some(priority)))
+                    .ok_or_else(|| format!("unknown priority {}, use a/b/c/d or none", value));
+            }
+            _ => return Err(format!("unknown filter field {}", field)),
+        };
+        match comparison {
+            Comparison::Equal | Comparison::Contains => {}
+            _ => return Err(format!("`{}` can't be compared with < or >", field)),
+        }
+        // `text:deploy` reads like "text contains deploy"
+        let comparison = match text_field {
+            TextField::Description => Comparison::Contains,
+            _ => comparison,
+        };
+        Ok(Term::Text(text_field, comparison, value.to_string()))
+    }
+
+    /// Dates are understood like the `-d` flag of `todo add`.
+    fn parse_date(value: &str) -> Result<NaiveDate, String> {
+        NaiveDate::parse_from_str(&parse_due_date(value.to_string()), "%d.%m.%Y")
+            .map_err(|_| format!("couldn't understand date: {}", value))
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum SortKey {
+        Due,
+        Created,
+        Creator,
+        Description,
+        Priority,
+    }
+    impl SortKey {
+        pub fn from_str(key: &str) -> Option<SortKey> {
+            match key {
+                "due" => Some(SortKey::Due),
+                "created" => Some(SortKey::Created),
+                "creator" => Some(SortKey::Creator),
+                "description" => Some(SortKey::Description),
+                "priority" => Some(SortKey::Priority),
+                _ => None,
+            }
+        }
+    }
+
+    /// Sorts `todos` by `key`. Todos that are equal keep their order.
+    /// Todos without due date or priority always go last when sorting by them.
+    pub fn sort_todos(todos: &mut [Todo], key: &SortKey, descending: bool) {
+        todos.sort_by(|a, b| {
+            let ordering = match key {
+                SortKey::Due => match (due_point(a), due_point(b)) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                SortKey::Priority => match (a.priority, b.priority) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                SortKey::Created => a.create_date.cmp(&b.create_date),
+                SortKey::Creator => a.created_by.cmp(&b.created_by),
+                SortKey::Description => a
+                    .description
+                    .to_lowercase()
+                    .cmp(&b.description.to_lowercase()),
+            };
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    /// A todo without due time is due at the end of its due date.
+    pub fn due_point(todo: &Todo) -> Option<NaiveDateTime> {
+        let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+        todo.due_date
+            .map(|date| date.and_time(todo.due_time.unwrap_or(end_of_day)))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::due_todo;
+
+        #[test]
+        fn test_filter_terms() {
+            let mut deploy = due_todo("Deploy the app", Some(0));
+            deploy.git_branch = Some("feature/deploy".to_string());
+            deploy.priority = Some(Priority::A);
+            let mut docs = due_todo("write docs +backend", Some(3));
+            docs.created_by = "bob".to_string();
+            let someday = due_todo("someday", None);
+
+            let cases = [
+                ("due:today", [true, false, false]),
+                ("due<\"in 2 days\"", [true, false, false]),
+                ("due>=tomorrow", [false, true, false]),
+                ("due:none", [false, false, true]),
+                ("by:alice", [true, false, true]),
+                ("text~deploy", [true, false, false]),
+                ("branch:feature/deploy", [true, false, false]),
+                ("branch~feature", [true, false, false]),
+                ("priority:high", [true, false, false]),
+                ("priority:none", [false, true, true]),
+                ("+Backend", [false, true, false]),
+                ("@alice", [false, false, false]),
+                ("status:open", [true, true, true]),
+                ("status:done", [false, false, false]),
+            ];
+            for (filter, expected) in cases {
+                let filter_parsed = parse_filter(filter).unwrap();
+                let matched = [&deploy, &docs, &someday].map(|todo| filter_parsed.matches(todo));
+                assert_eq!(matched, expected, "{}", filter);
+            }
+        }
+
+        #[test]
+        fn test_filter_combinations() {
+            let deploy = due_todo("deploy", Some(0));
+            let mut docs = due_todo("docs", Some(3));
+            docs.created_by = "bob".to_string();
+            let someday = due_todo("someday", None);
+
+            let cases = [
+                ("by:alice and not due:none", [true, false, false]),
+                ("by:alice due:none", [false, false, true]),
+                ("by:bob or due:today", [true, true, false]),
+                ("not (by:bob or due:today)", [false, false, true]),
+                (
+                    "by:alice and (text~docs or text~some)",
+                    [false, false, true],
+                ),
+            ];
+            for (filter, expected) in cases {
+                let filter_parsed = parse_filter(filter).unwrap();
+                let matched = [&deploy, &docs, &someday].map(|todo| filter_parsed.matches(todo));
+                assert_eq!(matched, expected, "{}", filter);
+            }
+        }
+
+        #[test]
+        fn test_sort_by_due() {
+            let mut later_today = due_todo("later today", Some(0));
+            later_today.due_time = NaiveTime::from_hms_opt(18, 0, 0);
+            let mut early_today = due_todo("early today", Some(0));
+            early_today.due_time = NaiveTime::from_hms_opt(8, 0, 0);
+            let today = due_todo("today", Some(0));
+            let tomorrow = due_todo("tomorrow", Some(1));
+            let someday = due_todo("someday", None);
+            let todos = vec![
+                someday.clone(),
+                tomorrow.clone(),
+                today.clone(),
+                later_today.clone(),
+                early_today.clone(),
+            ];
+
+            let mut ascending = todos.clone();
+            sort_todos(&mut ascending, &SortKey::Due, false);
+            let mut descending = todos;
+            sort_todos(&mut descending, &SortKey::Due, true);
+
+            assert_eq!(
+                ascending,
+                vec![
+                    early_today.clone(),
+                    later_today.clone(),
+                    today.clone(),
+                    tomorrow.clone(),
+                    someday.clone()
+                ]
+            );
+            assert_eq!(
+                descending,
+                vec![tomorrow, today, later_today, early_today, someday]
+            );
+        }
+
+        #[test]
+        fn test_sort_by_priority() {
+            let mut low = due_todo("low", None);
+            low.priority = Some(Priority::C);
+            let mut high = due_todo("high", None);
+            high.priority = Some(Priority::A);
+            let none = due_todo("none", None);
+
+            let mut ascending = vec![none.clone(), low.clone(), high.clone()];
+            sort_todos(&mut ascending, &SortKey::Priority, false);
+            let mut descending = ascending.clone();
+            sort_todos(&mut descending, &SortKey::Priority, true);
+
+            assert_eq!(ascending, vec![high.clone(), low.clone(), none.clone()]);
+            assert_eq!(descending, vec![low, high, none]);
+        }
+
+        #[test]
+        fn test_filter_errors() {
+            for filter in [
+                "",
+                "deploy",
+                "due:someday",
+                "due~today",
+                "status<open",
+                "priority<b",
+                "priority:e",
+                "colour:red",
+                "(by:alice",
+                "by:alice)",
+                "by:alice or",
+                "text~\"open quote",
+            ] {
+                assert!(parse_filter(filter).is_err(), "{}", filter);
+            }
+            assert!(!parse_filter("by:alice").unwrap().mentions_status());
+            assert!(parse_filter("not status:done").unwrap().mentions_status());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+pub mod git_handler {
+    use std::{
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    /// Runs `git` in `dir` and returns its trimmed output, or `None` if git failed
+    /// (not installed, not a repository, ...).
+    fn git(dir: &Path, args: &[&str]) -> Option<String> {
+        let output = Command::new("git")
+            // set while git runs a hook, they would point git away from `dir`
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_INDEX_FILE")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Whether `dir` is in the work tree of a git repository.
+    pub fn in_work_tree(dir: &Path) -> bool {
+        git(dir, &["rev-parse", "--is-inside-work-tree"]).is_some_and(|inside| inside == "true")
+    }
+
+    /// Name of the checked out branch. `None` outside a repository and for a detached HEAD.
+    pub fn current_branch(dir: &Path) -> Option<String> {
+        git(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+    }
+
+    /// Full hash of HEAD. `None` outside a repository and before the first commit.
+    pub fn head_commit(dir: &Path) -> Option<String> {
+        git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"])
+    }
+
+    /// Message of the HEAD commit.
+    pub fn last_commit_message(dir: &Path) -> Option<String> {
+        git(dir, &["log", "-1", "--format=%B"])
+    }
+
+    /// Sets `key` in the config of the repository around `dir`.
+    pub fn set_config(dir: &Path, key: &str, value: &str) -> Result<(), String> {
+        git(dir, &["config", key, value])
+            .map(|_| ())
+            .ok_or_else(|| format!("failed to set git config {}", key))
+    }
+
+    /// Directory git runs the hooks of the repository around `dir` from.
+    pub fn hooks_dir(dir: &Path) -> Option<PathBuf> {
+        git(dir, &["rev-parse", "--git-path", "hooks"]).map(|hooks| dir.join(hooks))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::{self, test_repo};
+        use std::fs;
+
+        #[test]
+        fn test_branch_and_commit() {
+            let Some(repo) = test_repo("git") else {
+                return;
+            };
+            git(&repo, &["symbolic-ref", "HEAD", "refs/heads/feature/login"]).unwrap();
+            let before_commit = (current_branch(&repo), head_commit(&repo));
+            assert!(test_helper::git(
+                &repo,
+                &["commit", "-q", "--allow-empty", "-m", "first"]
+            ));
+            let after_commit = (current_branch(&repo), head_commit(&repo));
+            git(&repo, &["checkout", "-q", "--detach"]).unwrap();
+            let detached = (current_branch(&repo), head_commit(&repo));
+            let outside = (current_branch(Path::new("/")), head_commit(Path::new("/")));
+            fs::remove_dir_all(&repo).unwrap();
+
+            assert_eq!(before_commit, (Some("feature/login".to_string()), None));
+            assert_eq!(after_commit.0.as_deref(), Some("feature/login"));
+            let hash = after_commit.1.unwrap();
+            assert_eq!(hash.len(), 40);
+            assert_eq!(detached, (None, Some(hash)));
+            assert_eq!(outside, (None, None));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+pub mod hook_handler {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use chrono::NaiveDateTime;
+
+    use crate::databasehandler::database_handler::todo_database::Todo;
+    use crate::filterhandler::filter_handler::due_point;
+    use crate::githandler::git_handler::hooks_dir;
+
+    /// The hooks `todo hooks install` writes.
+    pub const HOOKS: [&str; 3] = ["pre-commit", "pre-push", "post-commit"];
+    /// Open todos with this tag make the hooks complain.
+    pub const BLOCKING_TAG: &str = "+blocking";
+    /// Commit message trailer that finishes todos, e.g. `Closes-Todo: xm810w`.
+    pub const CLOSES_TRAILER: &str = "Closes-Todo";
+    /// Marks hooks written by todo, so installing again may replace them.
+    const HOOK_MARKER: &str = "# installed by `todo hooks install`";
+
+    /// What `install_hooks` did.
+    #[derive(Debug)]
+    pub struct InstallSummary {
+        pub hooks_dir: PathBuf,
+        pub installed: Vec<String>,
+        /// Hooks that already exist and weren't written by todo.
+        pub skipped: Vec<String>,
+    }
+
+    /// Writes the hooks into the git repository around `todo_dir`. They run `exe` in `todo_dir`.
+    ///
+    /// With `block` the pre-commit and pre-push hooks fail instead of only warning.
+    pub fn install_hooks(
+        todo_dir: &Path,
+        exe: &Path,
+        block: bool,
+    ) -> Result<InstallSummary, String> {
+        let hooks_dir = hooks_dir(todo_dir)
+            .ok_or_else(|| format!("{} is not in a git repository", todo_dir.to_string_lossy()))?;
+        fs::create_dir_all(&hooks_dir)
+            .map_err(|e| format!("failed to create {}: {}", hooks_dir.to_string_lossy(), e))?;
+
+        let mut summary = InstallSummary {
+            hooks_dir: hooks_dir.clone(),
+            installed: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for hook in HOOKS {
+            let path = hooks_dir.join(hook);
+            let existing = fs::read_to_string(&path).ok();
+            if existing.is_some_and(|script| !script.contains(HOOK_MARKER)) {
+                summary.skipped.push(hook.to_string());
+                continue;
+            }
+            fs::write(&path, hook_script(todo_dir, exe, hook, block))
+                .map_err(|e| format!("failed to write {}: {}", path.to_string_lossy(), e))?;
+            make_executable(&path)?;
+            summary.installed.push(hook.to_string());
+        }
+        Ok(summary)
+    }
+
+    fn hook_script(todo_dir: &Path, exe: &Path, hook: &str, block: bool) -> String {
+        format!(
+            "#!/bin/sh\n{}\ncd {} || exit 0\nexec {} hooks run {}{}\n",
+            HOOK_MARKER,
+            shell_quote(&todo_dir.to_string_lossy()),
+            shell_quote(&exe.to_string_lossy()),
+            hook,
+            if block { " --block" } else { "" }
+        )
+    }
+
+    /// `it's` -> `'it'\''s'`
+    fn shell_quote(text: &str) -> String {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+
+    #[cfg(unix)]
+    fn make_executable(path: &Path) -> Result<(), String> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).map_err(|e| {
+            format!(
+                "failed to make {} executable: {}",
+                path.to_string_lossy(),
+                e
+            )
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn make_executable(_path: &Path) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Open todos the pre-commit and pre-push hooks complain about, with the reason.
+    pub fn hook_problems(todos: &[Todo], now: NaiveDateTime) -> Vec<(Todo, &'static str)> {
+        todos
+            .iter()
+            .filter_map(|todo| {
+                let reason = if is_blocking(todo) {
+                    "blocking"
+                } else if due_point(todo).is_some_and(|due| due < now) {
+                    "overdue"
+                } else {
+                    return None;
+                };
+                Some((todo.clone(), reason))
+            })
+            .collect()
+    }
+
+    fn is_blocking(todo: &Todo) -> bool {
+        todo.tags.iter().any(|tag| tag == BLOCKING_TAG)
+    }
+
+    /// Ids in the `Closes-Todo:` trailers of a commit message. A trailer can list several
+    /// ids separated by spaces or commas.
+    pub fn closed_todo_ids(message: &str) -> Vec<String> {
+        message
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(key, _)| key.trim().eq_ignore_ascii_case(CLOSES_TRAILER))
+            .flat_map(|(_, ids)| {
+                ids.split([',', ' ', '\t'])
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>()
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::{due_todo, test_repo};
+        use chrono::Local;
+
+        #[test]
+        fn test_closed_todo_ids() {
+            let message = "fix login\n\ncloses-todo in the body doesn't count\n\
+                           Closes-Todo: xm810w\ncloses-todo: ab12cd, ef34gh\nSigned-off-by: bob\n";
+            assert_eq!(closed_todo_ids(message), vec!["xm810w", "ab12cd", "ef34gh"]);
+            assert!(closed_todo_ids("just a message").is_empty());
+        }
+
+        #[test]
+        fn test_hook_problems() {
+            let todos = [
+                due_todo("late", Some(-1)),
+                due_todo("fix the release +blocking", Some(5)),
+                due_todo("later", Some(1)),
+                due_todo("someday", None),
+            ];
+            let problems = hook_problems(&todos, Local::now().naive_local());
+            let reasons: Vec<(&str, &str)> = problems
+                .iter()
+                .map(|(todo, reason)| (todo.description.as_str(), *reason))
+                .collect();
+            assert_eq!(
+                reasons,
+                vec![
+                    ("late", "overdue"),
+                    ("fix the release +blocking", "blocking")
+                ]
+            );
+        }
+
+        #[test]
+        fn test_install_hooks_keeps_foreign_hooks() {
+            let Some(repo) = test_repo("hooks") else {
+                // git isn't installed
+                return;
+            };
+            let todo_dir = repo.join("it's here");
+            fs::create_dir_all(&todo_dir).unwrap();
+            let hooks = repo.join(".git/hooks");
+            fs::create_dir_all(&hooks).unwrap();
+            fs::write(hooks.join("pre-push"), "#!/bin/sh\nmake test\n").unwrap();
+
+            let exe = Path::new("/usr/bin/todo");
+            let first = install_hooks(&todo_dir, exe, false).unwrap();
+            let second = install_hooks(&todo_dir, exe, true).unwrap();
+            let pre_commit = fs::read_to_string(hooks.join("pre-commit")).unwrap();
+            let pre_push = fs::read_to_string(hooks.join("pre-push")).unwrap();
+            fs::remove_dir_all(&repo).unwrap();
+
+            assert_eq!(first.installed, vec!["pre-commit", "post-commit"]);
+            assert_eq!(first.skipped, vec!["pre-push"]);
+            assert_eq!(second.installed, vec!["pre-commit", "post-commit"]);
+            assert!(pre_commit.contains("it'\\''s here"));
+            assert!(pre_commit.ends_with("exec '/usr/bin/todo' hooks run pre-commit --block\n"));
+            assert_eq!(pre_push, "#!/bin/sh\nmake test\n");
+        }
+    }
+}
@@ -0,0 +1,336 @@
+pub mod journal_handler {
+    use std::path::{Path, PathBuf};
+
+    use chrono::{Local, NaiveDateTime};
+    use serde::{Deserialize, Serialize};
+
+    use crate::databasehandler::database_handler::todo_database::{Todo, TodoState};
+    use crate::filehandler::file_handler::{read_lines, write_lines};
+    use crate::storagehandler::storage_handler::get_storage;
+
+    /// Operations that can be undone, newest last.
+    pub const JOURNAL_FILE_NAME: &str = ".todo.journal";
+    /// Undone operations that can be redone, newest last.
+    pub const REDO_FILE_NAME: &str = ".todo.redo";
+    const MAX_OPERATIONS: usize = 100;
+
+    /// One todo before and after an operation. `None` means it didn't exist.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Change {
+        pub before: Option<Todo>,
+        pub after: Option<Todo>,
+    }
+
+    /// Everything one command changed.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Operation {
+        pub command: String,
+        pub date: NaiveDateTime,
+        pub changes: Vec<Change>,
+    }
+    impl Operation {
+        /// e.g. `finish "write docs"`
+        pub fn describe(&self) -> String {
+            let descriptions: Vec<String> = self
+                .changes
+                .iter()
+                .filter_map(|change| change.after.as_ref().or(change.before.as_ref()))
+                .map(|todo| format!("\"{}\"", todo.description))
+                .collect();
+            format!("{} {}", self.command, descriptions.join(", "))
+        }
+    }
+
+    fn journal_path(todo_dir: &Path, file_name: &str) -> PathBuf {
+        let mut path = todo_dir.to_path_buf();
+        path.push(file_name);
+        path
+    }
+
+    fn read_operations(todo_dir: &Path, file_name: &str) -> Result<Vec<Operation>, String> {
+        let path = journal_path(todo_dir, file_name);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        read_lines(&path)?
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("broken line in {}: {}", file_name, e))
+            })
+            .collect()
+    }
+
+    fn write_operations(
+        todo_dir: &Path,
+        file_name: &str,
+        operations: &[Operation],
+    ) -> Result<(), String> {
+        let skip = operations.len().saturating_sub(MAX_OPERATIONS);
+        let lines = operations
+            .iter()
+            .skip(skip)
+            .map(|operation| serde_json::to_string(operation).expect("operation is serializable"))
+            .collect();
+        write_lines(&journal_path(todo_dir, file_name), lines)
+    }
+
+    /// Remembers an operation so it can be undone. Clears everything that could be redone.
+    ///
+    /// The todos after the change are journaled as the storage returns them, which can differ
+    /// from `changes` (a tab file drops line breaks, due times are kept without seconds).
+    pub fn journal_record(
+        todo_dir: &Path,
+        command: &str,
+        changes: Vec<Change>,
+    ) -> Result<(), String> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let stored = load_all(todo_dir)?;
+        let changes = changes
+            .into_iter()
+            .map(|change| Change {
+                before: change.before,
+                after: change.after.map(|after| {
+                    stored
+                        .iter()
+                        .find(|todo| todo.id == after.id)
+                        .cloned()
+                        .unwrap_or(after)
+                }),
+            })
+            .collect();
+
+        let mut operations = read_operations(todo_dir, JOURNAL_FILE_NAME)?;
+        operations.push(Operation {
+            command: command.to_string(),
+            date: Local::now().naive_local(),
+            changes,
+        });
+        write_operations(todo_dir, JOURNAL_FILE_NAME, &operations)?;
+        write_operations(todo_dir, REDO_FILE_NAME, &[])
+    }
+
+    fn load_all(todo_dir: &Path) -> Result<Vec<Todo>, String> {
+        let storage = get_storage(todo_dir);
+        let mut todos = Vec::new();
+        for status in [TodoState::Open, TodoState::Done, TodoState::Deleted] {
+            todos.extend(storage.load(&status)?);
+        }
+        Ok(todos)
+    }
+
+    /// Reverses the last `count` operations. Returns the undone operations.
+    pub fn journal_undo(todo_dir: &Path, count: usize) -> Result<Vec<Operation>, String> {
+        move_operations(todo_dir, count, JOURNAL_FILE_NAME, REDO_FILE_NAME, true)
+    }
+
+    /// Replays the last `count` undone operations. Returns the redone operations.
+    pub fn journal_redo(todo_dir: &Path, count: usize) -> Result<Vec<Operation>, String> {
+        move_operations(todo_dir, count, REDO_FILE_NAME, JOURNAL_FILE_NAME, false)
+    }
+
+    fn move_operations(
+        todo_dir: &Path,
+        count: usize,
+        from_file: &str,
+        to_file: &str,
+        undo: bool,
+    ) -> Result<Vec<Operation>, String> {
+        let mut from = read_operations(todo_dir, from_file)?;
+        let mut to = read_operations(todo_dir, to_file)?;
+        let mut moved = Vec::new();
+
+        for _ in 0..count {
+            let operation = match from.pop() {
+                Some(operation) => operation,
+                None => break,
+            };
+            let steps: Vec<(&Option<Todo>, &Option<Todo>)> = if undo {
+                operation
+                    .changes
+                    .iter()
+                    .rev()
+                    .map(|change| (&change.after, &change.before))
+                    .collect()
+            } else {
+                operation
+                    .changes
+                    .iter()
+                    .map(|change| (&change.before, &change.after))
+                    .collect()
+            };
+            apply(todo_dir, &steps)?;
+            to.push(operation.clone());
+            moved.push(operation);
+
+            // written after every operation, so a failing one doesn't lose the others
+            write_operations(todo_dir, from_file, &from)?;
+            write_operations(todo_dir, to_file, &to)?;
+        }
+        Ok(moved)
+    }
+
+    /// Turns every stored todo of `steps` from its current into its target state, all at once.
+    ///
+    /// Fails without changing anything if one of them was changed since, so no later change
+    /// gets lost.
+    fn apply(todo_dir: &Path, steps: &[(&Option<Todo>, &Option<Todo>)]) -> Result<(), String> {
+        let stored = load_all(todo_dir)?;
+        // id, stored todo and todo after the steps so far
+        let mut todos: Vec<(String, Option<Todo>, Option<Todo>)> = Vec::new();
+        for (current, target) in steps {
+            let id = match current.as_ref().or(target.as_ref()) {
+                Some(todo) => todo.id.clone(),
+                None => continue,
+            };
+            let index = match todos.iter().position(|(known, _, _)| *known == id) {
+                Some(index) => index,
+                None => {
+                    let found = stored.iter().find(|todo| todo.id == id).cloned();
+                    todos.push((id.clone(), found.clone(), found));
+                    todos.len() - 1
+                }
+            };
+            if &todos[index].2 != *current {
+                return Err(format!(
+                    "todo {} was changed since, it can't be changed back",
+                    id
+                ));
+            }
+            todos[index].2 = (*target).clone();
+        }
+
+        let changes: Vec<(Option<Todo>, Option<Todo>)> = todos
+            .into_iter()
+            .filter(|(_, before, after)| before != after)
+            .map(|(_, before, after)| (before, after))
+            .collect();
+        get_storage(todo_dir).update_all(&changes)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::databasehandler::database_handler::todo_database::{
+            database_finish_todo, database_finish_todos, database_get_todos, database_inserte_todo,
+        };
+        use crate::testhelper::test_helper::{test_dir, test_todo};
+        use std::fs;
+
+        #[test]
+        fn test_undo_and_redo() {
+            let todo_dir = test_dir("journal");
+            let added = database_inserte_todo(&todo_dir, test_todo("undo me")).unwrap();
+            journal_record(
+                &todo_dir,
+                "add",
+                vec![Change {
+                    before: None,
+                    after: Some(added.clone()),
+                }],
+            )
+            .unwrap();
+            let finished =
+                database_finish_todo(&todo_dir, added.clone(), "bob".to_string()).unwrap();
+            journal_record(
+                &todo_dir,
+                "finish",
+                vec![Change {
+                    before: Some(added.clone()),
+                    after: Some(finished),
+                }],
+            )
+            .unwrap();
+
+            let undone = journal_undo(&todo_dir, 1).unwrap();
+            let open_after_undo = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            let redone = journal_redo(&todo_dir, 5).unwrap();
+            let open_after_redo = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            journal_undo(&todo_dir, 2).unwrap();
+            let all_after_undo = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(undone[0].command, "finish");
+            assert_eq!(open_after_undo, vec![added]);
+            assert_eq!(redone.len(), 1);
+            assert!(open_after_redo.is_empty());
+            assert!(all_after_undo.is_empty());
+        }
+
+        #[test]
+        fn test_failed_undo_changes_nothing() {
+            let todo_dir = test_dir("journal-partial");
+            let first = database_inserte_todo(&todo_dir, test_todo("first")).unwrap();
+            let second = database_inserte_todo(&todo_dir, test_todo("second")).unwrap();
+            let finished = database_finish_todos(
+                &todo_dir,
+                vec![first.clone(), second.clone()],
+                "bob".to_string(),
+            )
+            .unwrap();
+            journal_record(
+                &todo_dir,
+                "finish",
+                vec![
+                    Change {
+                        before: Some(first.clone()),
+                        after: Some(finished[0].clone()),
+                    },
+                    Change {
+                        before: Some(second),
+                        after: Some(finished[1].clone()),
+                    },
+                ],
+            )
+            .unwrap();
+            // changed behind the journal's back
+            let mut changed = finished[1].clone();
+            changed.description = "second, changed".to_string();
+            get_storage(&todo_dir)
+                .update(&finished[1], &changed)
+                .unwrap();
+
+            let failed = journal_undo(&todo_dir, 1);
+            let done_after_fail = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+            get_storage(&todo_dir)
+                .update(&changed, &finished[1])
+                .unwrap();
+            let undone = journal_undo(&todo_dir, 1).unwrap();
+            let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert!(failed.is_err());
+            assert_eq!(done_after_fail, vec![finished[0].clone(), changed]);
+            assert_eq!(undone.len(), 1);
+            assert_eq!(open.len(), 2);
+        }
+
+        #[test]
+        fn test_undo_of_todo_the_storage_changed() {
+            let todo_dir = test_dir("journal-lossy");
+            let mut todo = test_todo("two\nlines");
+            todo.due_date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1);
+            todo.due_time = chrono::NaiveTime::from_hms_opt(9, 30, 15);
+
+            let added = database_inserte_todo(&todo_dir, todo).unwrap();
+            journal_record(
+                &todo_dir,
+                "add",
+                vec![Change {
+                    before: None,
+                    after: Some(added),
+                }],
+            )
+            .unwrap();
+            let undone = journal_undo(&todo_dir, 1);
+            let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert!(undone.is_ok());
+            assert!(open.is_empty());
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! The todos of `todo`: the `.todo.*` storages, the `todo_database` functions on top
+//! of them and the handlers of the commands. `main.rs` only picks the todo directory and
+//! the handler.
+
+// `from_str` of the enums returns an `Option`, not the `Result` of `FromStr`
+#![allow(clippy::should_implement_trait)]
+
+use std::{env, path::PathBuf};
+
+pub mod brain;
+pub mod clapargs;
+pub mod databasehandler;
+pub mod exporthandler;
+pub mod filehandler;
+pub mod filterhandler;
+pub mod githandler;
+pub mod hookhandler;
+pub mod journalhandler;
+pub mod markdownhandler;
+pub mod mergehandler;
+pub mod scanhandler;
+pub mod storagehandler;
+#[cfg(test)]
+mod testhelper;
+
+pub fn get_current_working_dir() -> Result<PathBuf, String> {
+    let res = env::current_dir();
+    match res {
+        Ok(path) => Ok(path),
+        Err(_) => Err("Couldnt find current working dir!".to_string()),
+    }
+}
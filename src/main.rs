@@ -1,27 +1,37 @@
-use core::panic;
 use std::{
-    env,
     path::{Path, PathBuf},
     process::exit,
 };
 
-mod clapargs;
-use clapargs::clap_args::setup_cli_args;
-
-mod databasehandler;
-mod filehandler;
-
-use databasehandler::database_handler::todo_database::DatabaseField;
 use homedir::get_my_home;
 
-mod brain;
-use brain::handle_todo::{
-    display_todo, handle_add, handle_config, handle_create, handle_delete, handle_finish,
+use todo_cli::brain::handle_todo::{
+    handle_add, handle_config, handle_create, handle_delete, handle_edit, handle_export,
+    handle_finish, handle_hooks, handle_import, handle_list, handle_merge_driver,
+    handle_merge_setup, handle_migrate, handle_redo, handle_reopen, handle_restore, handle_scan,
+    handle_sync_md, handle_tags, handle_undo,
 };
+use todo_cli::clapargs::clap_args::setup_cli_args;
+use todo_cli::filehandler::file_handler::lock_dir;
+use todo_cli::get_current_working_dir;
 
 fn main() {
     let result = setup_cli_args();
 
+    // create doesn't need an existing todo directory
+    if let Some(("create", create_args)) = result.subcommand() {
+        handle_create(create_args);
+        exit(0);
+    }
+
+    // git runs the merge driver in the repository root, which needn't be a todo directory
+    if let Some(("merge-driver", merge_args)) = result.subcommand() {
+        if merge_args.contains_id("base") {
+            handle_merge_driver(merge_args);
+            exit(0);
+        }
+    }
+
     // Determine the correct todo directory based on global flag
     let todo_dir = if *result.get_one::<bool>("global").unwrap() {
         get_my_home()
@@ -32,18 +42,17 @@ fn main() {
             .expect("Could not find todo directory!")
     };
 
+    // serializes concurrent `todo` calls on the same todo directory
+    let _lock = lock_dir(&todo_dir).expect("Could not lock todo directory!");
+
     // Match on subcommands and handle appropriately
     match result.subcommand() {
-        Some(("create", create_args)) => {
-            handle_create(create_args);
-            exit(0);
-        }
         Some(("add", add_args)) => {
             handle_add(add_args, todo_dir);
             exit(0);
         }
-        Some(("list", _list_args)) => {
-            display_todo(false, todo_dir);
+        Some(("list", list_args)) => {
+            handle_list(list_args, todo_dir);
             exit(0);
         }
         Some(("open", _open_args)) => {
@@ -58,11 +67,63 @@ fn main() {
             handle_delete(delete_args, todo_dir);
             // Possibly exit or additional handling after delete
         }
+        Some(("undo", undo_args)) => {
+            handle_undo(undo_args, todo_dir);
+            exit(0);
+        }
+        Some(("redo", redo_args)) => {
+            handle_redo(redo_args, todo_dir);
+            exit(0);
+        }
+        Some(("import", import_args)) => {
+            handle_import(import_args, todo_dir);
+            exit(0);
+        }
+        Some(("export", export_args)) => {
+            handle_export(export_args, todo_dir);
+            exit(0);
+        }
+        Some(("sync-md", sync_args)) => {
+            handle_sync_md(sync_args, todo_dir);
+            exit(0);
+        }
+        Some(("scan", scan_args)) => {
+            handle_scan(scan_args, todo_dir);
+            exit(0);
+        }
+        Some(("hooks", hooks_args)) => {
+            handle_hooks(hooks_args, todo_dir);
+            exit(0);
+        }
+        Some(("merge-driver", _merge_args)) => {
+            handle_merge_setup(todo_dir);
+            exit(0);
+        }
+        Some(("migrate", migrate_args)) => {
+            handle_migrate(migrate_args, todo_dir);
+            exit(0);
+        }
+        Some(("tags", _tags_args)) => {
+            handle_tags(todo_dir);
+            exit(0);
+        }
+        Some(("edit", edit_args)) => {
+            handle_edit(edit_args, todo_dir);
+            exit(0);
+        }
+        Some(("reopen", reopen_args)) => {
+            handle_reopen(reopen_args, todo_dir);
+            exit(0);
+        }
+        Some(("restore", restore_args)) => {
+            handle_restore(restore_args, todo_dir);
+            exit(0);
+        }
         Some(("config", config_args)) => {
             handle_config(config_args);
             exit(0);
         }
-        Some(("clear", clear_args)) => {
+        Some(("clear", _clear_args)) => {
             println!("clear");
             // Additional handling for clear can be added here
         }
@@ -72,27 +133,22 @@ fn main() {
     }
 }
 
-fn get_current_working_dir() -> Result<PathBuf, String> {
-    let res = env::current_dir();
-    match res {
-        Ok(path) => Ok(path),
-        Err(_) => Err("Couldnt find current working dir!".to_string()),
-    }
-}
-
 fn get_todo_dir(current_dir: PathBuf) -> Result<PathBuf, String> {
     let mut todo_path = current_dir.clone();
     todo_path.push(".todo.todo");
+    // the sqlite storage keeps everything in .todo.db
+    let mut db_path = current_dir.clone();
+    db_path.push(".todo.db");
 
     if current_dir.clone() == Path::new("/") {
         println!("No todo directory was found. Please use the `create` command!");
         exit(0);
-    } else if todo_path.exists() {
+    } else if todo_path.exists() || db_path.exists() {
         Ok(current_dir)
     } else {
         match current_dir.parent() {
             None => panic!("Parent directory not found"),
-            Some(parent_dir) => return get_todo_dir(parent_dir.to_path_buf()),
+            Some(parent_dir) => get_todo_dir(parent_dir.to_path_buf()),
         }
     }
 }
@@ -0,0 +1,279 @@
+pub mod markdown_handler {
+    use std::path::{Path, PathBuf};
+
+    use chrono::Local;
+
+    use crate::databasehandler::database_handler::todo_database::{
+        database_apply_changes, database_get_todos, finished, Todo, TodoState,
+    };
+    use crate::filehandler::file_handler::{read_lines, write_lines};
+    use crate::journalhandler::journal_handler::Change;
+
+    const MARKER_START: &str = "<!-- todo:";
+    const MARKER_END: &str = "-->";
+
+    /// A `- [ ] description <!-- todo:id -->` line of a Markdown checklist.
+    #[derive(Debug, Clone, PartialEq)]
+    struct ChecklistItem {
+        /// Indentation and bullet, e.g. `  - `
+        prefix: String,
+        checked: bool,
+        description: String,
+        id: Option<String>,
+    }
+    impl ChecklistItem {
+        fn parse(line: &str) -> Option<ChecklistItem> {
+            let indent = line.len() - line.trim_start().len();
+            let rest = &line[indent..];
+            let bullet = rest.get(..2).filter(|b| ["- ", "* ", "+ "].contains(b))?;
+            let checked = match rest.get(2..6)? {
+                "[ ] " => false,
+                "[x] " | "[X] " => true,
+                _ => return None,
+            };
+            let mut description = rest[6..].trim();
+            let mut id = None;
+            if let Some(start) = description.rfind(MARKER_START) {
+                let marker = &description[start + MARKER_START.len()..];
+                if let Some(end) = marker.find(MARKER_END) {
+                    id = Some(marker[..end].trim().to_string());
+                    description = description[..start].trim_end();
+                }
+            }
+            Some(ChecklistItem {
+                prefix: format!("{}{}", &line[..indent], bullet),
+                checked,
+                description: description.to_string(),
+                id,
+            })
+        }
+
+        fn render(&self) -> String {
+            let mut line = format!(
+                "{}[{}] {}",
+                self.prefix,
+                if self.checked { "x" } else { " " },
+                self.description
+            );
+            if let Some(id) = &self.id {
+                line.push_str(&format!(" {}{} {}", MARKER_START, id, MARKER_END));
+            }
+            line
+        }
+
+        /// This item with the id, state and description of `todo`.
+        fn with_todo(mut self, todo: &Todo) -> ChecklistItem {
+            let from_todo = ChecklistItem::from_todo(todo);
+            self.id = from_todo.id;
+            self.checked = from_todo.checked;
+            self.description = from_todo.description;
+            self
+        }
+
+        fn from_todo(todo: &Todo) -> ChecklistItem {
+            ChecklistItem {
+                prefix: "- ".to_string(),
+                checked: todo.status == TodoState::Done,
+                description: todo.description.replace('\n', " "),
+                id: Some(todo.id.clone()),
+            }
+        }
+    }
+
+    /// What `sync_markdown` did.
+    #[derive(Debug, Default)]
+    pub struct SyncSummary {
+        pub changes: Vec<Change>,
+        pub added_to_file: usize,
+        pub unknown_ids: Vec<String>,
+    }
+
+    /// Reconciles the checklist in `file` with the open and finished todos:
+    ///
+    /// - items without id marker become todos (finished ones if checked) and get a marker
+    /// - checked items finish their todo, todos finished elsewhere get checked
+    /// - open todos that aren't in the file are appended to it
+    ///
+    /// The todos are the source of truth for descriptions. Lines that aren't checklist items
+    /// are kept as they are, so syncing twice changes nothing. All todos are changed at once,
+    /// before the file is written.
+    pub fn sync_markdown(
+        todo_dir: &Path,
+        file: &PathBuf,
+        changed_by: &str,
+    ) -> Result<SyncSummary, String> {
+        let old_lines = if file.exists() {
+            read_lines(file)?
+        } else {
+            Vec::new()
+        };
+        let mut open = database_get_todos(todo_dir, TodoState::Open)?;
+        let mut done = database_get_todos(todo_dir, TodoState::Done)?;
+        let find = |todos: &[Todo], id: &str| todos.iter().find(|todo| todo.id == id).cloned();
+
+        let mut summary = SyncSummary::default();
+        let mut changes = Vec::new();
+        // line, item and change of the items that become todos, their ids are known once stored
+        let mut added_items = Vec::new();
+        let mut seen_ids = Vec::new();
+        let mut lines = Vec::new();
+        for line in &old_lines {
+            let item = match ChecklistItem::parse(line) {
+                Some(item) => item,
+                None => {
+                    lines.push(line.clone());
+                    continue;
+                }
+            };
+
+            let todo = match &item.id {
+                None => {
+                    let mut todo = Todo::new(
+                        item.description.clone(),
+                        TodoState::Open,
+                        None,
+                        None,
+                        Local::now().naive_local(),
+                        changed_by.to_string(),
+                    );
+                    if item.checked {
+                        todo = finished(todo, changed_by.to_string());
+                    }
+                    added_items.push((lines.len(), item, changes.len()));
+                    changes.push((None, Some(todo)));
+                    lines.push(String::new());
+                    continue;
+                }
+                Some(id) => match find(&open, id).or_else(|| find(&done, id)) {
+                    Some(todo) => todo,
+                    None => {
+                        summary.unknown_ids.push(id.clone());
+                        lines.push(line.clone());
+                        continue;
+                    }
+                },
+            };
+
+            let todo = if item.checked && todo.status == TodoState::Open {
+                let finished_todo = finished(todo.clone(), changed_by.to_string());
+                // an item that is in the file twice finishes its todo once
+                open.retain(|stored| stored.id != todo.id);
+                done.push(finished_todo.clone());
+                changes.push((Some(todo), Some(finished_todo.clone())));
+                finished_todo
+            } else {
+                todo
+            };
+            seen_ids.push(todo.id.clone());
+            lines.push(item.with_todo(&todo).render());
+        }
+
+        let stored = database_apply_changes(todo_dir, changes.clone())?;
+        for (line, item, change) in added_items {
+            let todo = stored[change].as_ref().expect("added todos are stored");
+            lines[line] = item.with_todo(todo).render();
+        }
+        summary.changes = changes
+            .into_iter()
+            .zip(stored)
+            .map(|((before, _), after)| Change { before, after })
+            .collect();
+
+        for todo in open.iter().filter(|todo| !seen_ids.contains(&todo.id)) {
+            lines.push(ChecklistItem::from_todo(todo).render());
+            summary.added_to_file += 1;
+        }
+
+        if lines != old_lines {
+            write_lines(file, lines)?;
+        }
+        Ok(summary)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::databasehandler::database_handler::todo_database::{
+            database_get_all_todos, database_inserte_todo,
+        };
+        use crate::testhelper::test_helper::{test_dir, test_todo};
+        use std::fs;
+
+        #[test]
+        fn test_parse_and_render_items() {
+            let item = ChecklistItem::parse("  * [X] ship it <!-- todo:abc123 -->").unwrap();
+            assert_eq!(item.prefix, "  * ");
+            assert!(item.checked);
+            assert_eq!(item.description, "ship it");
+            assert_eq!(item.id.as_deref(), Some("abc123"));
+            assert_eq!(item.render(), "  * [x] ship it <!-- todo:abc123 -->");
+            assert_eq!(ChecklistItem::parse("- no checkbox"), None);
+            assert_eq!(ChecklistItem::parse("# [ ] heading"), None);
+        }
+
+        #[test]
+        fn test_sync_markdown() {
+            let todo_dir = test_dir("sync-md");
+            let file = todo_dir.join("TODO.md");
+            let from_cli = database_inserte_todo(&todo_dir, test_todo("added in the cli")).unwrap();
+            fs::write(
+                &file,
+                "# Todo\n\n- [ ] write docs\n- [x] already done\nsome text\n",
+            )
+            .unwrap();
+
+            let first = sync_markdown(&todo_dir, &file, "bob").unwrap();
+            let after_first = fs::read_to_string(&file).unwrap();
+            let second = sync_markdown(&todo_dir, &file, "bob").unwrap();
+            let after_second = fs::read_to_string(&file).unwrap();
+            let todos = database_get_all_todos(&todo_dir).unwrap();
+
+            // checking an item in the file finishes its todo
+            let checked = after_second.replace("- [ ] write docs", "- [x] write docs");
+            fs::write(&file, checked).unwrap();
+            let third = sync_markdown(&todo_dir, &file, "bob").unwrap();
+            let open_after_third = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(first.changes.len(), 2);
+            assert_eq!(first.added_to_file, 1);
+            assert!(second.changes.is_empty());
+            assert_eq!(second.added_to_file, 0);
+            assert_eq!(after_first, after_second);
+            assert_eq!(todos.len(), 3);
+            let lines: Vec<&str> = after_first.lines().collect();
+            assert_eq!(lines[0], "# Todo");
+            assert!(lines[2].starts_with("- [ ] write docs <!-- todo:"));
+            assert!(lines[3].starts_with("- [x] already done <!-- todo:"));
+            assert_eq!(lines[4], "some text");
+            assert_eq!(
+                lines[5],
+                format!("- [ ] added in the cli <!-- todo:{} -->", from_cli.id)
+            );
+            assert_eq!(third.changes.len(), 1);
+            assert_eq!(open_after_third, vec![from_cli]);
+        }
+
+        #[test]
+        fn test_sync_markdown_with_repeated_items() {
+            let todo_dir = test_dir("sync-md-repeated");
+            let file = todo_dir.join("TODO.md");
+            fs::write(&file, "- [ ] twice\n- [ ] twice\n").unwrap();
+
+            let first = sync_markdown(&todo_dir, &file, "bob").unwrap();
+            let added = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            // the same item checked twice
+            let line = format!("- [x] twice <!-- todo:{} -->", added[0].id);
+            fs::write(&file, format!("{}\n{}\n", line, line)).unwrap();
+            let second = sync_markdown(&todo_dir, &file, "bob");
+            let done = database_get_todos(&todo_dir, TodoState::Done).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(first.changes.len(), 2);
+            assert_eq!(added.len(), 2);
+            assert_ne!(added[0].id, added[1].id);
+            assert_eq!(second.unwrap().changes.len(), 1);
+            assert_eq!(done.len(), 1);
+        }
+    }
+}
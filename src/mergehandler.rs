@@ -0,0 +1,230 @@
+pub mod merge_handler {
+    use std::path::{Path, PathBuf};
+
+    use crate::databasehandler::database_handler::todo_database::{Todo, TodoState};
+    use crate::filehandler::file_handler::{read_lines, write_lines};
+    use crate::githandler::git_handler::set_config;
+    use crate::storagehandler::storage_handler::{
+        decode_lines, DecodedFile, LineFormat, TabFormat,
+    };
+
+    /// Name of the merge driver in the git config and `.gitattributes`.
+    const DRIVER_NAME: &str = "todo";
+
+    /// What `merge_todos` did.
+    #[derive(Debug, Default)]
+    pub struct MergeResult {
+        pub todos: Vec<Todo>,
+        /// Todos both sides changed in different ways.
+        pub conflicts: Vec<String>,
+    }
+
+    /// Three-way merge of the todos of one `.todo.*` file, todo by todo (same id).
+    ///
+    /// A todo only one side changed gets that change, added todos are kept and removed ones
+    /// stay removed. If both sides changed a todo the newer change wins (ours on the same
+    /// day). A todo changed on one side and removed on the other is removed too: it was
+    /// finished, deleted or restored into another `.todo.*` file, which keeps it, so every
+    /// todo ends up in one file. Only a todo both sides moved into different files ends up
+    /// in both, git doesn't run the driver for files only one side changed.
+    /// Our order is kept, todos only they have come last.
+    pub fn merge_todos(base: &[Todo], ours: &[Todo], theirs: &[Todo]) -> MergeResult {
+        let find = |todos: &[Todo], id: &str| todos.iter().find(|todo| todo.id == id).cloned();
+        let mut result = MergeResult::default();
+        let only_theirs = theirs.iter().filter(|todo| find(ours, &todo.id).is_none());
+
+        for todo in ours.iter().chain(only_theirs) {
+            let base_todo = find(base, &todo.id);
+            let our_todo = find(ours, &todo.id);
+            let their_todo = find(theirs, &todo.id);
+            let merged = if our_todo == their_todo || their_todo == base_todo {
+                our_todo
+            } else if our_todo == base_todo {
+                their_todo
+            } else if our_todo.is_none() || their_todo.is_none() {
+                // removed on one side and changed on the other
+                None
+            } else {
+                result.conflicts.push(todo.id.clone());
+                match (our_todo, their_todo) {
+                    (Some(our_todo), Some(their_todo))
+                        if their_todo.last_change_date > our_todo.last_change_date =>
+                    {
+                        Some(their_todo)
+                    }
+                    (our_todo, _) => our_todo,
+                }
+            };
+            result.todos.extend(merged);
+        }
+        result
+    }
+
+    /// Merges the versions of a `.todo.*` file git hands to a merge driver and writes the
+    /// result into `ours`, in the format `ours` has.
+    pub fn merge_files(base: &Path, ours: &Path, theirs: &Path) -> Result<MergeResult, String> {
+        let read = |file: &Path| -> Result<DecodedFile, String> {
+            decode_lines(&read_lines(&file.to_path_buf())?)
+                .map_err(|e| format!("{}: {}", file.to_string_lossy(), e))
+        };
+        let (_, base_todos) = read(base)?;
+        let (our_format, our_todos) = read(ours)?;
+        let (their_format, their_todos) = read(theirs)?;
+
+        let result = merge_todos(&base_todos, &our_todos, &their_todos);
+        let format: Box<dyn LineFormat> =
+            our_format.or(their_format).unwrap_or(Box::new(TabFormat));
+        let mut lines = vec![format.header()];
+        lines.extend(result.todos.iter().map(|todo| format.encode(todo)));
+        write_lines(&ours.to_path_buf(), lines)?;
+        Ok(result)
+    }
+
+    /// Configures the merge driver in the git repository around `todo_dir` and adds the
+    /// `.todo.*` files to `.gitattributes` in `todo_dir`. Returns the added attribute lines.
+    pub fn setup_merge_driver(todo_dir: &Path, exe: &Path) -> Result<Vec<String>, String> {
+        set_config(
+            todo_dir,
+            &format!("merge.{}.name", DRIVER_NAME),
+            "todo-cli merge of .todo.* files",
+        )?;
+        set_config(
+            todo_dir,
+            &format!("merge.{}.driver", DRIVER_NAME),
+            &format!(
+                "'{}' merge-driver %O %A %B",
+                exe.to_string_lossy().replace('\'', "'\\''")
+            ),
+        )?;
+
+        let attributes_path: PathBuf = todo_dir.join(".gitattributes");
+        let mut lines = if attributes_path.exists() {
+            read_lines(&attributes_path)?
+        } else {
+            Vec::new()
+        };
+        let mut added = Vec::new();
+        for status in [TodoState::Open, TodoState::Done, TodoState::Deleted] {
+            let file_name = status
+                .file_path(todo_dir)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let line = format!("{} merge={}", file_name, DRIVER_NAME);
+            if !lines.contains(&line) {
+                lines.push(line.clone());
+                added.push(line);
+            }
+        }
+        if !added.is_empty() {
+            write_lines(&attributes_path, lines)?;
+        }
+        Ok(added)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::storagehandler::storage_handler::JsonLinesFormat;
+        use crate::testhelper::test_helper::{fixed_todo_with_id, test_dir};
+        use chrono::NaiveDate;
+        use std::fs;
+
+        fn changed(todo: &Todo, description: &str, day: u32) -> Todo {
+            let mut todo = todo.clone();
+            todo.description = description.to_string();
+            todo.last_change_date = NaiveDate::from_ymd_opt(2024, 4, day).unwrap();
+            todo
+        }
+
+        #[test]
+        fn test_merge_todos() {
+            let kept = fixed_todo_with_id("kept01", "kept");
+            let removed = fixed_todo_with_id("remo01", "finished by us");
+            let edited = fixed_todo_with_id("edit01", "edited by them");
+            let both = fixed_todo_with_id("both01", "edited by both");
+            let base = vec![kept.clone(), removed.clone(), edited.clone(), both.clone()];
+
+            let ours = vec![
+                kept.clone(),
+                edited.clone(),
+                changed(&both, "ours", 23),
+                fixed_todo_with_id("ours01", "added by us"),
+            ];
+            let theirs = vec![
+                fixed_todo_with_id("thei01", "added by them"),
+                kept.clone(),
+                removed.clone(),
+                changed(&edited, "theirs", 22),
+                changed(&both, "theirs", 22),
+            ];
+
+            let result = merge_todos(&base, &ours, &theirs);
+            let descriptions: Vec<&str> = result
+                .todos
+                .iter()
+                .map(|todo| todo.description.as_str())
+                .collect();
+            assert_eq!(
+                descriptions,
+                vec!["kept", "theirs", "ours", "added by us", "added by them"]
+            );
+            assert_eq!(result.conflicts, vec!["both01"]);
+        }
+
+        #[test]
+        fn test_merge_todos_finish_against_edit() {
+            let open = fixed_todo_with_id("fini01", "finished by us");
+            let mut finished = changed(&open, "finished by us", 22);
+            finished.status = TodoState::Done;
+            let base = vec![open.clone()];
+            let edited = vec![changed(&open, "edited by them", 23)];
+            let moved = vec![finished];
+
+            // we moved it from .todo.todo to .todo.finished, they changed it in .todo.todo
+            let todo_file = merge_todos(&base, &[], &edited);
+            let finished_file = merge_todos(&[], &moved, &[]);
+            // they finished it and we changed it
+            let their_todo_file = merge_todos(&base, &edited, &[]);
+            let their_finished_file = merge_todos(&[], &[], &moved);
+
+            assert!(todo_file.todos.is_empty());
+            assert!(todo_file.conflicts.is_empty());
+            assert_eq!(finished_file.todos, moved);
+            assert!(their_todo_file.todos.is_empty());
+            assert_eq!(their_finished_file.todos, moved);
+        }
+
+        #[test]
+        fn test_merge_files_keeps_our_format() {
+            let dir = test_dir("merge");
+            let first = fixed_todo_with_id("firs01", "first");
+            let second = fixed_todo_with_id("seco01", "second");
+            let write = |name: &str, format: &dyn LineFormat, todos: &[&Todo]| {
+                let mut lines = vec![format.header()];
+                lines.extend(todos.iter().map(|todo| format.encode(todo)));
+                write_lines(&dir.join(name), lines).unwrap();
+            };
+            write("base", &TabFormat, &[]);
+            write("ours", &JsonLinesFormat, &[&first]);
+            write("theirs", &TabFormat, &[&second]);
+            fs::write(dir.join("legacy"), "bob\told todo\n").unwrap();
+
+            let result = merge_files(&dir.join("base"), &dir.join("ours"), &dir.join("theirs"));
+            let merged = read_lines(&dir.join("ours")).unwrap();
+            let legacy = merge_files(&dir.join("legacy"), &dir.join("ours"), &dir.join("theirs"));
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(result.unwrap().todos, vec![first.clone(), second.clone()]);
+            assert_eq!(
+                merged,
+                vec![
+                    JsonLinesFormat.header(),
+                    JsonLinesFormat.encode(&first),
+                    JsonLinesFormat.encode(&second)
+                ]
+            );
+            assert!(legacy.is_err());
+        }
+    }
+}
@@ -0,0 +1,548 @@
+pub mod scan_handler {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use chrono::Local;
+
+    use crate::databasehandler::database_handler::todo_database::{
+        database_apply_changes, database_get_todos, finished, Todo, TodoState,
+    };
+    use crate::journalhandler::journal_handler::Change;
+
+    const KEYWORDS: [&str; 3] = ["TODO", "FIXME", "XXX"];
+    const COMMENT_STARTS: [&str; 6] = ["//", "/*", "#", "--", ";", "*"];
+    const MACROS: [&str; 2] = ["todo", "unimplemented"];
+    /// Directories that hold dependencies or build output, not project code.
+    const SKIPPED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+    /// A `TODO`, `FIXME` or `XXX` comment found in the code, or a `todo!()` or
+    /// `unimplemented!()` call in Rust code.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Comment {
+        /// Relative to the todo directory, always with `/`.
+        pub path: String,
+        /// Starts at 1.
+        pub line: usize,
+        pub text: String,
+    }
+    impl Comment {
+        /// e.g. `src/main.rs:12`
+        pub fn source(&self) -> String {
+            format!("{}:{}", self.path, self.line)
+        }
+    }
+
+    /// What `sync_comments` did.
+    #[derive(Debug, Default)]
+    pub struct ScanSummary {
+        pub changes: Vec<Change>,
+        pub added: usize,
+        /// Open todos whose comment isn't in the code anymore.
+        pub gone: Vec<Todo>,
+    }
+
+    /// Returns all comments in the files below `start`. Paths are relative to `root`.
+    ///
+    /// Hidden files and directories, `target` and `node_modules` are skipped, just like
+    /// files that aren't text. In `.rs` files `todo!()` and `unimplemented!()` count too.
+    pub fn scan_comments(root: &Path, start: &Path) -> Result<Vec<Comment>, String> {
+        let mut comments = Vec::new();
+        for file in collect_files(start)? {
+            let content = match fs::read_to_string(&file) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let path = relative_path(root, &file);
+            let mut found: Vec<(usize, String)> = content
+                .lines()
+                .enumerate()
+                .filter_map(|(index, line)| find_comment(line).map(|text| (index + 1, text)))
+                .collect();
+            if file.extension().is_some_and(|extension| extension == "rs") {
+                found.extend(find_macros(&content));
+                found.sort_by_key(|(line, _)| *line);
+            }
+            comments.extend(found.into_iter().map(|(line, text)| Comment {
+                path: path.clone(),
+                line,
+                text,
+            }));
+        }
+        Ok(comments)
+    }
+
+    fn collect_files(start: &Path) -> Result<Vec<PathBuf>, String> {
+        if start.is_file() {
+            return Ok(vec![start.to_path_buf()]);
+        }
+        let mut entries: Vec<PathBuf> = fs::read_dir(start)
+            .map_err(|e| format!("failed to read {}: {}", start.to_string_lossy(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        entries.sort();
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let name = entry
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if name.starts_with('.') || entry.is_symlink() {
+                continue;
+            }
+            if entry.is_dir() {
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    files.extend(collect_files(&entry)?);
+                }
+            } else {
+                files.push(entry);
+            }
+        }
+        Ok(files)
+    }
+
+    pub fn relative_path(root: &Path, file: &Path) -> String {
+        let relative = file.strip_prefix(root).unwrap_or(file);
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Returns the text of a `// TODO: text` comment in `line`.
+    /// The keyword has to start the comment, so code and prose mentioning TODO is skipped.
+    pub fn find_comment(line: &str) -> Option<String> {
+        for (position, _) in line.char_indices() {
+            let rest = &line[position..];
+            let start = match COMMENT_STARTS
+                .iter()
+                .find(|start| rest.starts_with(**start))
+            {
+                Some(start) => start,
+                None => continue,
+            };
+            // inside a string literal, `\"` doesn't end it
+            let quotes =
+                line[..position].matches('"').count() - line[..position].matches("\\\"").count();
+            if quotes % 2 == 1 {
+                continue;
+            }
+            let comment =
+                rest[start.len()..].trim_start_matches(['/', '*', '#', '!', '-', ';', ' ', '\t']);
+            let text = comment_text(comment);
+            // the rest of the line belongs to this comment, e.g. `/// the `// TODO` marker`
+            if text.is_some() || ["//", "/*"].contains(start) {
+                return text;
+            }
+        }
+        None
+    }
+
+    /// `TODO(bob): fix it */` -> `fix it`
+    fn comment_text(comment: &str) -> Option<String> {
+        let keyword = KEYWORDS
+            .iter()
+            .find(|keyword| comment.starts_with(**keyword))?;
+        let mut rest = &comment[keyword.len()..];
+        if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+        if rest.starts_with('(') {
+            rest = rest.find(')').map_or("", |end| &rest[end + 1..]);
+        }
+        let text = rest
+            .trim_start_matches([':', ' ', '\t'])
+            .trim_end()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        if text.is_empty() {
+            return Some(keyword.to_string());
+        }
+        Some(text.to_string())
+    }
+
+    /// Returns the line and text of every `todo!()` and `unimplemented!()` call in Rust code.
+    ///
+    /// The text is the macro message after the name of the enclosing function, like
+    /// `database_inserte_todo: store it`. Without message the macro itself is used:
+    /// `database_inserte_todo: todo!()`. Comments, strings and char literals are skipped.
+    pub fn find_macros(content: &str) -> Vec<(usize, String)> {
+        let chars: Vec<char> = content.chars().collect();
+        let line_of = |index: usize| chars[..index].iter().filter(|c| **c == '\n').count() + 1;
+        let mut found = Vec::new();
+        let mut depth = 0;
+        // name and brace depth of the body of the functions around the current position
+        let mut functions: Vec<(String, usize)> = Vec::new();
+        // a `fn name` whose body hasn't started yet
+        let mut pending: Option<String> = None;
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '/' if chars.get(i + 1) == Some(&'/') => {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+                '/' if chars.get(i + 1) == Some(&'*') => i = skip_block_comment(&chars, i),
+                '"' => i = read_string(&chars, i, 0).1,
+                '\'' => i = skip_char(&chars, i),
+                '{' => {
+                    depth += 1;
+                    if let Some(name) = pending.take() {
+                        functions.push((name, depth));
+                    }
+                    i += 1;
+                }
+                '}' => {
+                    if functions.last().is_some_and(|(_, body)| *body == depth) {
+                        functions.pop();
+                    }
+                    depth = depth.saturating_sub(1);
+                    i += 1;
+                }
+                ';' => {
+                    // a function without body, like in a trait
+                    pending = None;
+                    i += 1;
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let start = i;
+                    let word = read_word(&chars, &mut i);
+                    match word.as_str() {
+                        "r" | "br" if matches!(chars.get(i), Some('"') | Some('#')) => {
+                            let hashes = chars[i..].iter().take_while(|c| **c == '#').count();
+                            i = read_string(&chars, i + hashes, hashes).1;
+                        }
+                        "fn" => {
+                            let mut next = skip_whitespace(&chars, i);
+                            if chars
+                                .get(next)
+                                .is_some_and(|c| c.is_alphabetic() || *c == '_')
+                            {
+                                pending = Some(read_word(&chars, &mut next));
+                                i = next;
+                            }
+                        }
+                        name if MACROS.contains(&name) => {
+                            let bang = skip_whitespace(&chars, i);
+                            if chars.get(bang) != Some(&'!') {
+                                continue;
+                            }
+                            let open = skip_whitespace(&chars, bang + 1);
+                            if !matches!(chars.get(open), Some('(') | Some('[') | Some('{')) {
+                                continue;
+                            }
+                            let argument = skip_whitespace(&chars, open + 1);
+                            let message = match (chars.get(argument), chars.get(argument + 1)) {
+                                (Some('"'), _) => read_string(&chars, argument, 0).0,
+                                (Some('r'), Some('"') | Some('#')) => {
+                                    let hashes = chars[argument + 1..]
+                                        .iter()
+                                        .take_while(|c| **c == '#')
+                                        .count();
+                                    read_string(&chars, argument + 1 + hashes, hashes).0
+                                }
+                                _ => String::new(),
+                            };
+                            let message = match message.trim() {
+                                "" => format!("{}!()", name),
+                                message => message.to_string(),
+                            };
+                            let text = match functions.last() {
+                                Some((function, _)) => format!("{}: {}", function, message),
+                                None => message,
+                            };
+                            found.push((line_of(start), text));
+                            i = open;
+                        }
+                        _ => {}
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        found
+    }
+
+    fn read_word(chars: &[char], i: &mut usize) -> String {
+        let start = *i;
+        while *i < chars.len() && (chars[*i].is_alphanumeric() || chars[*i] == '_') {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect()
+    }
+
+    fn skip_whitespace(chars: &[char], mut i: usize) -> usize {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    }
+
+    /// Reads the string starting with the `"` at `start`, closed by `"` and `hashes` times `#`.
+    /// Escapes are only resolved in normal strings. Returns the content and the index after it.
+    fn read_string(chars: &[char], start: usize, hashes: usize) -> (String, usize) {
+        let mut content = String::new();
+        let mut i = start + 1;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' if hashes == 0 && i + 1 < chars.len() => {
+                    content.push(match chars[i + 1] {
+                        'n' => '\n',
+                        't' => '\t',
+                        escaped => escaped,
+                    });
+                    i += 2;
+                    continue;
+                }
+                '"' if chars[i + 1..]
+                    .iter()
+                    .take(hashes)
+                    .filter(|c| **c == '#')
+                    .count()
+                    == hashes =>
+                {
+                    return (content, i + 1 + hashes);
+                }
+                c => content.push(c),
+            }
+            i += 1;
+        }
+        (content, i)
+    }
+
+    /// Block comments can be nested in Rust.
+    fn skip_block_comment(chars: &[char], start: usize) -> usize {
+        let mut nesting = 0;
+        let mut i = start;
+        while i + 1 < chars.len() {
+            match (chars[i], chars[i + 1]) {
+                ('/', '*') => {
+                    nesting += 1;
+                    i += 2;
+                }
+                ('*', '/') => {
+                    nesting -= 1;
+                    i += 2;
+                    if nesting == 0 {
+                        return i;
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        chars.len()
+    }
+
+    /// Skips a char literal like `'x'` or `'\''`. Lifetimes like `'a` are only a `'`.
+    fn skip_char(chars: &[char], start: usize) -> usize {
+        if chars.get(start + 1) == Some(&'\\') {
+            let mut i = start + 3;
+            while i < chars.len() && chars[i] != '\'' {
+                i += 1;
+            }
+            return i + 1;
+        }
+        if chars.get(start + 2) == Some(&'\'') {
+            return start + 3;
+        }
+        start + 1
+    }
+
+    /// Adds a todo for every new comment and keeps the line of known ones up to date.
+    ///
+    /// A todo belongs to a comment if it has the same text in the same file. Open todos
+    /// from files below `scanned` whose comment is gone are returned in `gone`. They are
+    /// finished if `finish_gone` is set. All todos are changed at once.
+    pub fn sync_comments(
+        todo_dir: &Path,
+        comments: Vec<Comment>,
+        scanned: &str,
+        changed_by: &str,
+        finish_gone: bool,
+    ) -> Result<ScanSummary, String> {
+        let mut tracked: Vec<Todo> = database_get_todos(todo_dir, TodoState::Open)?
+            .into_iter()
+            .chain(database_get_todos(todo_dir, TodoState::Done)?)
+            .filter(|todo| todo.source.is_some())
+            .collect();
+        let mut summary = ScanSummary::default();
+        let mut changes = Vec::new();
+
+        for comment in comments {
+            let known = tracked.iter().position(|todo| {
+                todo.description == comment.text
+                    && todo.source.as_deref().and_then(source_path) == Some(comment.path.as_str())
+            });
+            match known {
+                Some(position) => {
+                    let todo = tracked.remove(position);
+                    if todo.source.as_deref() != Some(comment.source().as_str()) {
+                        let mut moved = todo.clone();
+                        moved.source = Some(comment.source());
+                        changes.push((Some(todo), Some(moved)));
+                    }
+                }
+                None => {
+                    let mut todo = Todo::new(
+                        comment.text.clone(),
+                        TodoState::Open,
+                        None,
+                        None,
+                        Local::now().naive_local(),
+                        changed_by.to_string(),
+                    );
+                    todo.source = Some(comment.source());
+                    changes.push((None, Some(todo)));
+                    summary.added += 1;
+                }
+            }
+        }
+
+        for todo in tracked {
+            let path = todo
+                .source
+                .as_deref()
+                .and_then(source_path)
+                .unwrap_or_default();
+            let below_scanned =
+                scanned.is_empty() || path == scanned || path.starts_with(&format!("{}/", scanned));
+            if todo.status != TodoState::Open || !below_scanned {
+                continue;
+            }
+            if finish_gone {
+                changes.push((
+                    Some(todo.clone()),
+                    Some(finished(todo.clone(), changed_by.to_string())),
+                ));
+            }
+            summary.gone.push(todo);
+        }
+
+        let stored = database_apply_changes(todo_dir, changes.clone())?;
+        summary.changes = changes
+            .into_iter()
+            .zip(stored)
+            .map(|((before, _), after)| Change { before, after })
+            .collect();
+        Ok(summary)
+    }
+
+    /// `src/main.rs:12` -> `src/main.rs`
+    fn source_path(source: &str) -> Option<&str> {
+        source.rsplit_once(':').map(|(path, _)| path)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::test_dir;
+
+        #[test]
+        fn test_find_comment() {
+            let cases = [
+                ("    // TODO: fix this", Some("fix this")),
+                ("x = 1 # FIXME handle zero", Some("handle zero")),
+                ("/* XXX(bob): later */", Some("later")),
+                (" * TODO", Some("TODO")),
+                ("-- TODO: sql too", Some("sql too")),
+                ("let s = \"// TODO: not a comment\";", None),
+                ("(\"s = \\\"// TODO: escaped\\\"\", None)", None),
+                ("/// the `// TODO: text` marker", None),
+                ("// TODOS are great", None),
+                ("// this is not a TODO", None),
+                ("todo!(\"rust macro\")", None),
+            ];
+            for (line, expected) in cases {
+                assert_eq!(find_comment(line).as_deref(), expected, "{}", line);
+            }
+        }
+
+        #[test]
+        fn test_find_macros() {
+            let code = r#"
+trait Store {
+    fn save(&self);
+}
+
+impl Store for Db {
+    fn save(&self) {
+        // todo!("in a comment")
+        let s = "todo!(\"in a string\")";
+        let c = '{';
+        todo!("write \"it\"")
+    }
+}
+
+fn load<'a>(name: &'a str) -> &'a str {
+    let parse = |s: &str| unimplemented!();
+    todo!(r"raw {}", name)
+}
+
+const X: u8 = todo!();
+"#;
+            assert_eq!(
+                find_macros(code),
+                vec![
+                    (11, "save: write \"it\"".to_string()),
+                    (16, "load: unimplemented!()".to_string()),
+                    (17, "load: raw {}".to_string()),
+                    (20, "todo!()".to_string()),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_scan_and_sync_comments() {
+            let todo_dir = test_dir("scan");
+            fs::create_dir_all(todo_dir.join("src")).unwrap();
+            fs::create_dir_all(todo_dir.join("target")).unwrap();
+            fs::write(
+                todo_dir.join("src/main.rs"),
+                "fn main() {}\n// TODO: a\n// FIXME: b\n",
+            )
+            .unwrap();
+            fs::write(todo_dir.join("target/gen.rs"), "// TODO: generated\n").unwrap();
+
+            let comments = scan_comments(&todo_dir, &todo_dir).unwrap();
+            let first = sync_comments(&todo_dir, comments.clone(), "", "bob", false).unwrap();
+            let second = sync_comments(&todo_dir, comments, "", "bob", false).unwrap();
+
+            // `a` moved down a line and `b` was fixed
+            fs::write(todo_dir.join("src/main.rs"), "fn main() {}\n\n// TODO: a\n").unwrap();
+            let comments = scan_comments(&todo_dir, &todo_dir.join("src")).unwrap();
+            let third = sync_comments(&todo_dir, comments, "src", "bob", true).unwrap();
+            let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(first.added, 2);
+            assert_eq!(second.changes.len(), 0);
+            assert_eq!(third.added, 0);
+            assert_eq!(third.gone.len(), 1);
+            assert_eq!(third.gone[0].description, "b");
+            assert_eq!(open.len(), 1);
+            assert_eq!(open[0].source.as_deref(), Some("src/main.rs:3"));
+        }
+
+        #[test]
+        fn test_sync_repeated_comments() {
+            let todo_dir = test_dir("scan-repeated");
+            fs::write(todo_dir.join("lib.rs"), "// TODO: same\n// TODO: same\n").unwrap();
+
+            let comments = scan_comments(&todo_dir, &todo_dir).unwrap();
+            let first = sync_comments(&todo_dir, comments.clone(), "", "bob", false).unwrap();
+            let second = sync_comments(&todo_dir, comments, "", "bob", false).unwrap();
+            let open = database_get_todos(&todo_dir, TodoState::Open).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(first.added, 2);
+            assert!(second.changes.is_empty());
+            assert_eq!(open.len(), 2);
+            assert_ne!(open[0].id, open[1].id);
+        }
+    }
+}
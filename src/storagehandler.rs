@@ -0,0 +1,1186 @@
+pub mod storage_handler {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    use rusqlite::{params, types::Type, Connection, ErrorCode, Row};
+
+    use crate::databasehandler::database_handler::todo_database::{
+        generate_id, tags_in, DatabaseField, Priority, Todo, TodoState, FORMAT_VERSION,
+    };
+    use crate::filehandler::file_handler::{append_line, read_lines, remove_line, write_lines};
+
+    /// Where the todos of a todo directory are kept.
+    ///
+    /// `todo_database` only talks to this trait, so the storage can be swapped per project.
+    pub trait TodoStorage {
+        /// Creates whatever is missing. Returns the names of the created files.
+        fn create(&self) -> Result<Vec<String>, String>;
+        /// Returns all todos with `status` in the order they were added.
+        fn load(&self, status: &TodoState) -> Result<Vec<Todo>, String>;
+        /// Replaces all todos with `status` by `todos`.
+        fn save(&self, status: &TodoState, todos: &[Todo]) -> Result<(), String>;
+        fn insert(&self, todo: &Todo) -> Result<(), String>;
+        /// Replaces the stored `old_todo` (found by id) with `new_todo`,
+        /// which may have another state.
+        fn update(&self, old_todo: &Todo, new_todo: &Todo) -> Result<(), String>;
+        /// The files this storage keeps its todos in.
+        fn files(&self) -> Vec<PathBuf>;
+
+        /// Returns all todos where `field` matches.
+        fn query(&self, field: &DatabaseField) -> Result<Vec<Todo>, String> {
+            load_matching(self, field)
+        }
+
+        /// Replaces every old todo (found by id) with its new todo. Without old todo the new
+        /// one is added, without new todo the old one is removed. Nothing is written if one of
+        /// the old todos isn't stored or one of the added ones is.
+        fn update_all(&self, changes: &[(Option<Todo>, Option<Todo>)]) -> Result<(), String> {
+            let states = [TodoState::Open, TodoState::Done, TodoState::Deleted];
+            let mut lists = Vec::new();
+            for status in &states {
+                lists.push(self.load(status)?);
+            }
+            let mut changed = [false; 3];
+            let mut gained = [false; 3];
+            let list_index = |status: &TodoState| states.iter().position(|s| s == status).unwrap();
+
+            for (old_todo, new_todo) in changes {
+                if let Some(old_todo) = old_todo {
+                    let old_list = list_index(&old_todo.status);
+                    let position = lists[old_list]
+                        .iter()
+                        .position(|stored| stored.id == old_todo.id)
+                        .ok_or_else(|| format!("todo not found: {}", old_todo.id))?;
+                    changed[old_list] = true;
+                    match new_todo {
+                        // keeps its place in the list
+                        Some(new_todo) if new_todo.status == old_todo.status => {
+                            lists[old_list][position] = new_todo.clone();
+                            continue;
+                        }
+                        _ => {
+                            lists[old_list].remove(position);
+                        }
+                    }
+                }
+                if let Some(new_todo) = new_todo {
+                    if old_todo.is_none()
+                        && lists
+                            .iter()
+                            .flatten()
+                            .any(|stored| stored.id == new_todo.id)
+                    {
+                        return Err(format!("todo {} already exists", new_todo.id));
+                    }
+                    let new_list = list_index(&new_todo.status);
+                    lists[new_list].push(new_todo.clone());
+                    changed[new_list] = true;
+                    gained[new_list] = true;
+                }
+            }
+
+            // the lists todos move into first, so a crash in between duplicates moved todos
+            // instead of losing them
+            for first in [true, false] {
+                for (index, status) in states.iter().enumerate() {
+                    if changed[index] && gained[index] == first {
+                        self.save(status, &lists[index])?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn load_matching<S: TodoStorage + ?Sized>(
+        storage: &S,
+        field: &DatabaseField,
+    ) -> Result<Vec<Todo>, String> {
+        let mut todos = Vec::new();
+        for status in [TodoState::Open, TodoState::Done, TodoState::Deleted] {
+            todos.extend(
+                storage
+                    .load(&status)?
+                    .into_iter()
+                    .filter(|todo| field.matches(todo)),
+            );
+        }
+        Ok(todos)
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum StorageKind {
+        Tab,
+        JsonLines,
+        Sqlite,
+    }
+    impl StorageKind {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                StorageKind::Tab => "tab",
+                StorageKind::JsonLines => "jsonl",
+                StorageKind::Sqlite => "sqlite",
+            }
+        }
+
+        pub fn from_str(kind: &str) -> Option<StorageKind> {
+            match kind.trim() {
+                "tab" => Some(StorageKind::Tab),
+                "jsonl" | "json" => Some(StorageKind::JsonLines),
+                "sqlite" => Some(StorageKind::Sqlite),
+                _ => None,
+            }
+        }
+    }
+
+    /// Reads the storage from the third line of `.todo.config`. Defaults to `tab`.
+    pub fn configured_storage_kind(todo_dir: &Path) -> StorageKind {
+        let mut config_path = todo_dir.to_path_buf();
+        config_path.push(".todo.config");
+        read_lines(&config_path)
+            .ok()
+            .and_then(|lines| lines.get(2).and_then(|line| StorageKind::from_str(line)))
+            .unwrap_or(StorageKind::Tab)
+    }
+
+    /// Returns the storage configured for `todo_dir`.
+    pub fn get_storage(todo_dir: &Path) -> Box<dyn TodoStorage> {
+        open_storage(todo_dir, &configured_storage_kind(todo_dir))
+    }
+
+    pub fn open_storage(todo_dir: &Path, kind: &StorageKind) -> Box<dyn TodoStorage> {
+        match kind {
+            StorageKind::Tab => Box::new(LineStorage::new(todo_dir, TabFormat)),
+            StorageKind::JsonLines => Box::new(LineStorage::new(todo_dir, JsonLinesFormat)),
+            StorageKind::Sqlite => Box::new(SqliteStorage::new(todo_dir)),
+        }
+    }
+
+    const STAGING_DIR_NAME: &str = ".todo.migrate";
+
+    /// Copies all todos of `todo_dir` from the `from` storage into the `to` storage.
+    /// Returns how many todos were copied.
+    ///
+    /// All todos are read before anything is written. The new files are written into
+    /// `.todo.migrate` first and only replace the files of the todo directory once every state
+    /// was read back complete, so on an error the todos are still stored in `from`. Removing
+    /// the files only `from` used is left to the caller.
+    pub fn convert_storage(
+        todo_dir: &Path,
+        from: &StorageKind,
+        to: &StorageKind,
+    ) -> Result<usize, String> {
+        let states = [TodoState::Open, TodoState::Done, TodoState::Deleted];
+        let old_storage = open_storage(todo_dir, from);
+        let mut lists = Vec::new();
+        for status in &states {
+            lists.push(old_storage.load(status)?);
+        }
+
+        let staging_dir = todo_dir.join(STAGING_DIR_NAME);
+        if staging_dir.exists() {
+            fs::remove_dir_all(&staging_dir)
+                .map_err(|e| format!("failed to remove {}: {}", STAGING_DIR_NAME, e))?;
+        }
+        fs::create_dir(&staging_dir)
+            .map_err(|e| format!("failed to create {}: {}", STAGING_DIR_NAME, e))?;
+        let converted = stage_todos(&staging_dir, to, &states, &lists).and_then(|()| {
+            let staged_files = open_storage(&staging_dir, to).files();
+            for (staged, file) in staged_files.iter().zip(open_storage(todo_dir, to).files()) {
+                if staged.exists() {
+                    fs::rename(staged, &file)
+                        .map_err(|e| format!("failed to move {}: {}", file.to_string_lossy(), e))?;
+                }
+            }
+            Ok(lists.iter().map(|todos| todos.len()).sum())
+        });
+        // the staged files are gone after a successful move, whatever is left is useless
+        let _ = fs::remove_dir_all(&staging_dir);
+        converted
+    }
+
+    /// Writes `lists` into a new `kind` storage in `staging_dir` and checks they all arrived.
+    fn stage_todos(
+        staging_dir: &Path,
+        kind: &StorageKind,
+        states: &[TodoState],
+        lists: &[Vec<Todo>],
+    ) -> Result<(), String> {
+        let staged = open_storage(staging_dir, kind);
+        staged.create()?;
+        for (status, todos) in states.iter().zip(lists) {
+            staged.save(status, todos)?;
+            let written = staged.load(status)?.len();
+            if written != todos.len() {
+                return Err(format!(
+                    "only {} of {} {} todos could be written",
+                    written,
+                    todos.len(),
+                    status.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// How a todo is written into one line of a `.todo.*` file.
+    pub trait LineFormat {
+        /// First line of every file in this format.
+        fn header(&self) -> String;
+        /// Returns the format version if `line` is a header of this format.
+        fn header_version(&self, line: &str) -> Option<u32>;
+        fn encode(&self, todo: &Todo) -> String;
+        fn decode(&self, line: &str) -> Result<Todo, String>;
+    }
+
+    fn line_formats() -> Vec<Box<dyn LineFormat>> {
+        vec![Box::new(TabFormat), Box::new(JsonLinesFormat)]
+    }
+
+    /// The format of a file and its todos.
+    pub type DecodedFile = (Option<Box<dyn LineFormat>>, Vec<Todo>);
+
+    /// Decodes the lines of a `.todo.*` file in any line format, like the versions git hands
+    /// to the merge driver. Returns the format of the file, which empty files don't have.
+    pub fn decode_lines(lines: &[String]) -> Result<DecodedFile, String> {
+        let first_line = match lines.iter().find(|line| !line.trim().is_empty()) {
+            Some(line) => line,
+            None => return Ok((None, Vec::new())),
+        };
+        let format = line_formats()
+            .into_iter()
+            .find(|format| format.header_version(first_line).is_some())
+            .ok_or("the file has no format header, run any todo command to upgrade it")?;
+        if format.header_version(first_line) > Some(FORMAT_VERSION) {
+            return Err("the file was written by a newer version of todo".to_string());
+        }
+        let todos = lines
+            .iter()
+            .skip_while(|line| line.trim().is_empty())
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| format.decode(line))
+            .collect::<Result<Vec<Todo>, String>>()?;
+        Ok((Some(format), todos))
+    }
+
+    /// Stores the todos in `.todo.todo`, `.todo.finished` and `.todo.deleted`, one per line.
+    pub struct LineStorage<F: LineFormat> {
+        todo_dir: PathBuf,
+        format: F,
+    }
+    impl<F: LineFormat> LineStorage<F> {
+        pub fn new(todo_dir: &Path, format: F) -> LineStorage<F> {
+            LineStorage {
+                todo_dir: todo_dir.to_path_buf(),
+                format,
+            }
+        }
+
+        /// Reads all lines of the file of `status` including the format header.
+        ///
+        /// Files in another format or an older version are converted in place.
+        fn read_file(&self, status: &TodoState) -> Result<Vec<String>, String> {
+            let file = status.file_path(&self.todo_dir);
+            let lines = read_lines(&file)?;
+            let first_line = lines.first().map(|line| line.as_str()).unwrap_or("");
+
+            if let Some(version) = self.format.header_version(first_line) {
+                if version > FORMAT_VERSION {
+                    return Err(format!(
+                        "{} was written by a newer version of todo (format {})",
+                        file.to_string_lossy(),
+                        version
+                    ));
+                }
+                if version == FORMAT_VERSION {
+                    return Ok(lines);
+                }
+            }
+
+            let todos = match line_formats()
+                .into_iter()
+                .find(|format| format.header_version(first_line).is_some())
+            {
+                Some(format) => lines
+                    .iter()
+                    .skip(1)
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| format.decode(line))
+                    .collect::<Result<Vec<Todo>, String>>()?,
+                // version 1 had no header and only six columns
+                None => lines
+                    .iter()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| decode_legacy_line(line, status.clone()))
+                    .collect::<Result<Vec<Todo>, String>>()?,
+            };
+            let converted = self.to_lines(&todos);
+            write_lines(&file, converted.clone())?;
+            Ok(converted)
+        }
+
+        fn to_lines(&self, todos: &[Todo]) -> Vec<String> {
+            let mut lines = vec![self.format.header()];
+            lines.extend(todos.iter().map(|todo| self.format.encode(todo)));
+            lines
+        }
+
+        /// Returns the line index of `todo` in the file of its state.
+        fn find(&self, todo: &Todo) -> Result<usize, String> {
+            let lines = self.read_file(&todo.status)?;
+            for (index, line) in lines.iter().enumerate().skip(1) {
+                if let Ok(stored) = self.format.decode(line) {
+                    if stored.id == todo.id {
+                        return Ok(index);
+                    }
+                }
+            }
+            Err(format!("todo not found: {}", todo.id))
+        }
+    }
+    impl<F: LineFormat> TodoStorage for LineStorage<F> {
+        fn create(&self) -> Result<Vec<String>, String> {
+            let mut created = Vec::new();
+            for status in [TodoState::Open, TodoState::Done, TodoState::Deleted] {
+                let file = status.file_path(&self.todo_dir);
+                if !file.exists() {
+                    fs::write(&file, format!("{}\n", self.format.header()))
+                        .map_err(|e| format!("failed to create file: {}", e))?;
+                    created.push(status.file_name().to_string());
+                }
+            }
+            Ok(created)
+        }
+
+        fn load(&self, status: &TodoState) -> Result<Vec<Todo>, String> {
+            if !status.file_path(&self.todo_dir).exists() {
+                return Ok(Vec::new());
+            }
+            self.read_file(status)?
+                .iter()
+                .skip(1)
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| self.format.decode(line))
+                .collect()
+        }
+
+        fn save(&self, status: &TodoState, todos: &[Todo]) -> Result<(), String> {
+            write_lines(&status.file_path(&self.todo_dir), self.to_lines(todos))
+        }
+
+        fn insert(&self, todo: &Todo) -> Result<(), String> {
+            let file = todo.status.file_path(&self.todo_dir);
+            if !file.exists() {
+                self.create()?;
+            }
+            // converts the file first if needed
+            self.read_file(&todo.status)?;
+            append_line(&file, self.format.encode(todo))
+        }
+
+        fn update(&self, old_todo: &Todo, new_todo: &Todo) -> Result<(), String> {
+            let index = self.find(old_todo)?;
+            if old_todo.status != new_todo.status {
+                // inserted first, so a crash in between can't lose the todo
+                self.insert(new_todo)?;
+                return remove_line(&old_todo.status.file_path(&self.todo_dir), index);
+            }
+
+            let mut lines = self.read_file(&old_todo.status)?;
+            lines[index] = self.format.encode(new_todo);
+            write_lines(&old_todo.status.file_path(&self.todo_dir), lines)
+        }
+
+        fn files(&self) -> Vec<PathBuf> {
+            [TodoState::Open, TodoState::Done, TodoState::Deleted]
+                .iter()
+                .map(|status| status.file_path(&self.todo_dir))
+                .collect()
+        }
+    }
+
+    const DATE_FORMAT: &str = "%Y-%m-%d";
+    const TIME_FORMAT: &str = "%H:%M";
+    const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+    const TAB_HEADER_PREFIX: &str = "# todo-cli format ";
+
+    /// Tab separated columns: id, description, status, due_date, due_time, done_date,
+    /// create_date, created_by, last_changed_by, last_change_date, source, git_branch,
+    /// git_commit, priority, tags
+    ///
+    /// The columns after last_change_date are optional. Tags are separated by spaces, without
+    /// them a todo has the tags of its description.
+    pub struct TabFormat;
+    impl LineFormat for TabFormat {
+        fn header(&self) -> String {
+            format!("{}{}", TAB_HEADER_PREFIX, FORMAT_VERSION)
+        }
+
+        fn header_version(&self, line: &str) -> Option<u32> {
+            line.strip_prefix(TAB_HEADER_PREFIX)?.trim().parse().ok()
+        }
+
+        fn encode(&self, todo: &Todo) -> String {
+            // tabs and line breaks would split the record
+            let clean = |value: &str| value.replace(['\t', '\n', '\r'], " ");
+            [
+                todo.id.clone(),
+                clean(&todo.description),
+                todo.status.as_str().to_string(),
+                format_optional(todo.due_date.map(|d| d.format(DATE_FORMAT))),
+                format_optional(todo.due_time.map(|t| t.format(TIME_FORMAT))),
+                format_optional(todo.done_date.map(|d| d.format(DATE_FORMAT))),
+                todo.create_date.format(DATE_TIME_FORMAT).to_string(),
+                clean(&todo.created_by),
+                clean(&todo.last_changed_by),
+                todo.last_change_date.format(DATE_FORMAT).to_string(),
+                clean(todo.source.as_deref().unwrap_or_default()),
+                clean(todo.git_branch.as_deref().unwrap_or_default()),
+                clean(todo.git_commit.as_deref().unwrap_or_default()),
+                format_optional(todo.priority.map(|p| p.as_str())),
+                todo.tags.join(" "),
+            ]
+            .join("\t")
+        }
+
+        fn decode(&self, line: &str) -> Result<Todo, String> {
+            let values: Vec<&str> = line.split('\t').collect();
+            if values.len() < 10 {
+                return Err(format!("broken todo line: {}", line));
+            }
+            let broken = |field: &str| format!("broken {} in todo line: {}", field, line);
+            let optional = |index: usize| {
+                values
+                    .get(index)
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+            let description = values[1].to_string();
+            Ok(Todo {
+                id: values[0].to_string(),
+                tags: optional(14)
+                    .map(|tags| tags.split(' ').map(|tag| tag.to_string()).collect())
+                    .unwrap_or_else(|| tags_in(&description)),
+                description,
+                status: TodoState::from_str(values[2]).ok_or_else(|| broken("status"))?,
+                due_date: parse_optional(values[3], |d| NaiveDate::parse_from_str(d, DATE_FORMAT))
+                    .map_err(|_| broken("due_date"))?,
+                due_time: parse_optional(values[4], |t| NaiveTime::parse_from_str(t, TIME_FORMAT))
+                    .map_err(|_| broken("due_time"))?,
+                done_date: parse_optional(values[5], |d| NaiveDate::parse_from_str(d, DATE_FORMAT))
+                    .map_err(|_| broken("done_date"))?,
+                create_date: NaiveDateTime::parse_from_str(values[6], DATE_TIME_FORMAT)
+                    .map_err(|_| broken("create_date"))?,
+                created_by: values[7].to_string(),
+                last_changed_by: values[8].to_string(),
+                last_change_date: NaiveDate::parse_from_str(values[9], DATE_FORMAT)
+                    .map_err(|_| broken("last_change_date"))?,
+                source: optional(10),
+                git_branch: optional(11),
+                git_commit: optional(12),
+                priority: optional(13)
+                    .map(|p| Priority::from_str(&p).ok_or_else(|| broken("priority")))
+                    .transpose()?,
+            })
+        }
+    }
+
+    fn format_optional<T: std::fmt::Display>(value: Option<T>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_default()
+    }
+
+    fn parse_optional<T, E>(
+        value: &str,
+        parse: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<Option<T>, E> {
+        if value.is_empty() {
+            return Ok(None);
+        }
+        parse(value).map(Some)
+    }
+
+    /// One JSON object per line with the fields of `Todo`.
+    pub struct JsonLinesFormat;
+    impl LineFormat for JsonLinesFormat {
+        fn header(&self) -> String {
+            format!(
+                "{{\"todo_cli_format\":{},\"storage\":\"jsonl\"}}",
+                FORMAT_VERSION
+            )
+        }
+
+        fn header_version(&self, line: &str) -> Option<u32> {
+            let header: serde_json::Value = serde_json::from_str(line).ok()?;
+            header.get("todo_cli_format")?.as_u64().map(|v| v as u32)
+        }
+
+        fn encode(&self, todo: &Todo) -> String {
+            serde_json::to_string(todo).expect("todo can always be serialized")
+        }
+
+        fn decode(&self, line: &str) -> Result<Todo, String> {
+            let mut todo: Todo = serde_json::from_str(line)
+                .map_err(|e| format!("broken todo line: {} ({})", line, e))?;
+            if todo.tags.is_empty() {
+                todo.tags = tags_in(&todo.description);
+            }
+            Ok(todo)
+        }
+    }
+
+    const SQLITE_FILE_NAME: &str = ".todo.db";
+    const SQLITE_COLUMNS: &str = "id, description, status, due_date, due_time, done_date, \
+        create_date, created_by, last_changed_by, last_change_date, source, git_branch, git_commit, priority, tags";
+    /// Columns added after the table was first created, added to older databases when opened.
+    const SQLITE_OPTIONAL_COLUMNS: [&str; 5] =
+        ["source", "git_branch", "git_commit", "priority", "tags"];
+    /// Schema version of `.todo.db`, kept in `PRAGMA user_version`. Up to 3 it was the
+    /// format version, which doesn't change for new optional fields. New columns do.
+    const SQLITE_SCHEMA_VERSION: u32 = 4;
+
+    /// Stores all todos in one table of `.todo.db`.
+    ///
+    /// Unlike the line storages a change only touches its own row.
+    pub struct SqliteStorage {
+        db_path: PathBuf,
+    }
+    impl SqliteStorage {
+        pub fn new(todo_dir: &Path) -> SqliteStorage {
+            let mut db_path = todo_dir.to_path_buf();
+            db_path.push(SQLITE_FILE_NAME);
+            SqliteStorage { db_path }
+        }
+
+        /// Opens `.todo.db`. Databases with an older schema are migrated first.
+        fn connect(&self) -> Result<Connection, String> {
+            let mut connection = Connection::open(&self.db_path)
+                .map_err(|e| format!("failed to open {}: {}", SQLITE_FILE_NAME, e))?;
+            let version: u32 = connection
+                .query_row("PRAGMA user_version", [], |row| row.get(0))
+                .map_err(sqlite_error)?;
+            if version > SQLITE_SCHEMA_VERSION {
+                return Err(format!(
+                    "{} was written by a newer version of todo (schema {})",
+                    SQLITE_FILE_NAME, version
+                ));
+            }
+            if version < SQLITE_SCHEMA_VERSION {
+                migrate(&mut connection)?;
+            }
+            Ok(connection)
+        }
+
+        fn select(
+            &self,
+            condition: &str,
+            value: &dyn rusqlite::ToSql,
+        ) -> Result<Vec<Todo>, String> {
+            let connection = self.connect()?;
+            let mut statement = connection
+                .prepare(&format!(
+                    "SELECT {} FROM todos WHERE {} ORDER BY rowid",
+                    SQLITE_COLUMNS, condition
+                ))
+                .map_err(sqlite_error)?;
+            let rows = statement
+                .query_map([value], todo_from_row)
+                .map_err(sqlite_error)?;
+            rows.collect::<Result<Vec<Todo>, _>>().map_err(sqlite_error)
+        }
+    }
+    impl TodoStorage for SqliteStorage {
+        fn create(&self) -> Result<Vec<String>, String> {
+            let existed = self.db_path.exists();
+            self.connect()?;
+            if existed {
+                return Ok(Vec::new());
+            }
+            Ok(vec![SQLITE_FILE_NAME.to_string()])
+        }
+
+        fn load(&self, status: &TodoState) -> Result<Vec<Todo>, String> {
+            self.select("status = ?1", &status.as_str())
+        }
+
+        fn save(&self, status: &TodoState, todos: &[Todo]) -> Result<(), String> {
+            let mut connection = self.connect()?;
+            let transaction = connection.transaction().map_err(sqlite_error)?;
+            transaction
+                .execute("DELETE FROM todos WHERE status = ?1", [status.as_str()])
+                .map_err(sqlite_error)?;
+            for todo in todos {
+                insert_row(&transaction, todo)?;
+            }
+            transaction.commit().map_err(sqlite_error)
+        }
+
+        fn insert(&self, todo: &Todo) -> Result<(), String> {
+            insert_row(&self.connect()?, todo)
+        }
+
+        fn update(&self, old_todo: &Todo, new_todo: &Todo) -> Result<(), String> {
+            let mut connection = self.connect()?;
+            let transaction = connection.transaction().map_err(sqlite_error)?;
+            let removed = transaction
+                .execute("DELETE FROM todos WHERE id = ?1", [&old_todo.id])
+                .map_err(sqlite_error)?;
+            if removed == 0 {
+                return Err(format!("todo not found: {}", old_todo.id));
+            }
+            insert_row(&transaction, new_todo)?;
+            transaction.commit().map_err(sqlite_error)
+        }
+
+        fn files(&self) -> Vec<PathBuf> {
+            vec![self.db_path.clone()]
+        }
+
+        fn update_all(&self, changes: &[(Option<Todo>, Option<Todo>)]) -> Result<(), String> {
+            let mut connection = self.connect()?;
+            let transaction = connection.transaction().map_err(sqlite_error)?;
+            for (old_todo, new_todo) in changes {
+                if let Some(old_todo) = old_todo {
+                    let removed = transaction
+                        .execute("DELETE FROM todos WHERE id = ?1", [&old_todo.id])
+                        .map_err(sqlite_error)?;
+                    if removed == 0 {
+                        return Err(format!("todo not found: {}", old_todo.id));
+                    }
+                }
+                if let Some(new_todo) = new_todo {
+                    insert_row(&transaction, new_todo)?;
+                }
+            }
+            transaction.commit().map_err(sqlite_error)
+        }
+
+        fn query(&self, field: &DatabaseField) -> Result<Vec<Todo>, String> {
+            // these columns are indexed
+            match field {
+                DatabaseField::Id(id) => self.select("id = ?1", id),
+                DatabaseField::Status(status) => self.select("status = ?1", &status.as_str()),
+                DatabaseField::CreatedBy(name) => self.select("created_by = ?1", name),
+                DatabaseField::DueDate(Some(date)) => {
+                    self.select("due_date = ?1", &date.format(DATE_FORMAT).to_string())
+                }
+                DatabaseField::DueDate(None) => self.select("due_date IS ?1", &None::<String>),
+                _ => load_matching(self, field),
+            }
+        }
+    }
+
+    /// Creates the table and adds missing columns in one transaction, then stores the schema
+    /// version, so this only runs once per database.
+    fn migrate(connection: &mut Connection) -> Result<(), String> {
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        transaction
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS todos (
+                    id TEXT PRIMARY KEY,
+                    description TEXT NOT NULL,
+                    status TEXT NOT NULL,
+                    due_date TEXT,
+                    due_time TEXT,
+                    done_date TEXT,
+                    create_date TEXT NOT NULL,
+                    created_by TEXT NOT NULL,
+                    last_changed_by TEXT NOT NULL,
+                    last_change_date TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS todos_status ON todos (status);
+                CREATE INDEX IF NOT EXISTS todos_due ON todos (due_date, due_time);
+                CREATE INDEX IF NOT EXISTS todos_created_by ON todos (created_by);",
+            )
+            .map_err(sqlite_error)?;
+
+        let existing: Vec<String> = transaction
+            .prepare("SELECT name FROM pragma_table_info('todos')")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()
+            })
+            .map_err(sqlite_error)?;
+        for column in SQLITE_OPTIONAL_COLUMNS {
+            if !existing.iter().any(|name| name == column) {
+                transaction
+                    .execute_batch(&format!("ALTER TABLE todos ADD COLUMN {} TEXT", column))
+                    .map_err(sqlite_error)?;
+            }
+        }
+        transaction
+            .execute_batch(&format!("PRAGMA user_version = {}", SQLITE_SCHEMA_VERSION))
+            .map_err(sqlite_error)?;
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    fn sqlite_error(e: rusqlite::Error) -> String {
+        format!("sqlite error: {}", e)
+    }
+
+    fn insert_row(connection: &Connection, todo: &Todo) -> Result<(), String> {
+        connection
+            .execute(
+                &format!(
+                    "INSERT INTO todos ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                    SQLITE_COLUMNS
+                ),
+                params![
+                    todo.id,
+                    todo.description,
+                    todo.status.as_str(),
+                    todo.due_date.map(|d| d.format(DATE_FORMAT).to_string()),
+                    todo.due_time.map(|t| t.format(TIME_FORMAT).to_string()),
+                    todo.done_date.map(|d| d.format(DATE_FORMAT).to_string()),
+                    todo.create_date.format(DATE_TIME_FORMAT).to_string(),
+                    todo.created_by,
+                    todo.last_changed_by,
+                    todo.last_change_date.format(DATE_FORMAT).to_string(),
+                    todo.source,
+                    todo.git_branch,
+                    todo.git_commit,
+                    todo.priority.map(|p| p.as_str()),
+                    (!todo.tags.is_empty()).then(|| todo.tags.join(" ")),
+                ],
+            )
+            .map_err(|e| match e.sqlite_error_code() {
+                // the id is the primary key
+                Some(ErrorCode::ConstraintViolation) => format!("todo {} already exists", todo.id),
+                _ => sqlite_error(e),
+            })?;
+        Ok(())
+    }
+
+    /// Fails on values todo didn't write, instead of guessing what they mean.
+    fn todo_from_row(row: &Row) -> rusqlite::Result<Todo> {
+        let date = |d: &str| NaiveDate::parse_from_str(d, DATE_FORMAT).ok();
+        let description: String = row.get(1)?;
+        Ok(Todo {
+            id: row.get(0)?,
+            tags: row
+                .get::<_, Option<String>>(14)?
+                .map(|tags| tags.split(' ').map(|tag| tag.to_string()).collect())
+                .unwrap_or_else(|| tags_in(&description)),
+            description,
+            status: parse_required(row, 2, TodoState::from_str)?,
+            due_date: parse_column(row, 3, date)?,
+            due_time: parse_column(row, 4, |t| NaiveTime::parse_from_str(t, TIME_FORMAT).ok())?,
+            done_date: parse_column(row, 5, date)?,
+            create_date: parse_required(row, 6, |d| {
+                NaiveDateTime::parse_from_str(d, DATE_TIME_FORMAT).ok()
+            })?,
+            created_by: row.get(7)?,
+            last_changed_by: row.get(8)?,
+            last_change_date: parse_required(row, 9, date)?,
+            source: row.get(10)?,
+            git_branch: row.get(11)?,
+            git_commit: row.get(12)?,
+            priority: parse_column(row, 13, Priority::from_str)?,
+        })
+    }
+
+    /// Parses column `index` of `row`. `NULL` is `None`, a value `parse` can't read an error.
+    fn parse_column<T>(
+        row: &Row,
+        index: usize,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> rusqlite::Result<Option<T>> {
+        row.get::<_, Option<String>>(index)?
+            .map(|value| parse(&value).ok_or_else(|| broken_value(index, &value)))
+            .transpose()
+    }
+
+    /// Like `parse_column` for `NOT NULL` columns.
+    fn parse_required<T>(
+        row: &Row,
+        index: usize,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> rusqlite::Result<T> {
+        let value: String = row.get(index)?;
+        parse(&value).ok_or_else(|| broken_value(index, &value))
+    }
+
+    fn broken_value(index: usize, value: &str) -> rusqlite::Error {
+        rusqlite::Error::FromSqlConversionFailure(
+            index,
+            Type::Text,
+            format!("broken value {:?}", value).into(),
+        )
+    }
+
+    /// Parses one line of a file without format header (version 1).
+    /// The state is given by the file the line is in.
+    ///
+    /// columns: created_by, description, due_date, due_time, create date, create time, id
+    fn decode_legacy_line(line: &str, status: TodoState) -> Result<Todo, String> {
+        let values: Vec<&str> = line.split('\t').collect();
+        if values.len() < 2 {
+            return Err(format!("broken todo line: {}", line));
+        }
+        let created_by = values[0].to_string();
+        let description = values[1].to_string();
+        let due_date = values
+            .get(2)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%d.%m.%Y").ok());
+        let due_time = values
+            .get(3)
+            .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok());
+
+        // older versions wrote the time into the date column and the other way around
+        let date = values.get(4).copied().unwrap_or("");
+        let time = values.get(5).copied().unwrap_or("");
+        let create_date =
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%y-%m-%d %H:%M")
+                .or_else(|_| {
+                    NaiveDateTime::parse_from_str(&format!("{} {}", time, date), "%y-%m-%d %H:%M")
+                })
+                .unwrap_or_default();
+
+        let mut todo = Todo::new(
+            description,
+            status,
+            due_date,
+            due_time,
+            create_date,
+            created_by,
+        );
+        todo.last_change_date = create_date.date();
+        // lines written before ids existed get one derived from their content,
+        // so it stays the same as long as the line isn't changed
+        todo.id = match values.get(6) {
+            Some(id) if !id.is_empty() => id.to_string(),
+            _ => generate_id(line),
+        };
+        Ok(todo)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testhelper::test_helper::{fixed_todo, test_dir};
+
+        /// `fixed_todo` with the fields it leaves empty set too.
+        fn done_todo() -> Todo {
+            let mut todo = fixed_todo("write tests");
+            todo.status = TodoState::Done;
+            todo.due_time = NaiveTime::from_hms_opt(14, 0, 0);
+            todo.done_date = NaiveDate::from_ymd_opt(2024, 4, 30);
+            todo.last_changed_by = "bob".to_string();
+            todo
+        }
+
+        #[test]
+        fn test_tab_round_trip() {
+            let todo = done_todo();
+            assert_eq!(TabFormat.decode(&TabFormat.encode(&todo)), Ok(todo));
+        }
+
+        #[test]
+        fn test_json_lines_round_trip() {
+            let mut todo = done_todo();
+            todo.description = "with\ttab and\nnewline".to_string();
+            let line = JsonLinesFormat.encode(&todo);
+            assert!(!line.contains('\n'));
+            assert_eq!(JsonLinesFormat.decode(&line), Ok(todo));
+        }
+
+        #[test]
+        fn test_headers_are_told_apart() {
+            assert_eq!(
+                TabFormat.header_version(&TabFormat.header()),
+                Some(FORMAT_VERSION)
+            );
+            assert_eq!(TabFormat.header_version(&JsonLinesFormat.header()), None);
+            assert_eq!(
+                JsonLinesFormat.header_version(&JsonLinesFormat.header()),
+                Some(FORMAT_VERSION)
+            );
+            assert_eq!(JsonLinesFormat.header_version(&TabFormat.header()), None);
+        }
+
+        #[test]
+        fn test_legacy_line_gets_stable_id() {
+            let line = "bob\tlegacy thing\t01.05.2024\t12:00\t14:22\t24-04-20";
+            let first = decode_legacy_line(line, TodoState::Open).unwrap();
+            let second = decode_legacy_line(line, TodoState::Open).unwrap();
+            assert_eq!(first.id, second.id);
+            assert_eq!(
+                first.create_date,
+                NaiveDate::from_ymd_opt(2024, 4, 20)
+                    .unwrap()
+                    .and_hms_opt(14, 22, 0)
+                    .unwrap()
+            );
+        }
+
+        #[test]
+        fn test_legacy_file_is_migrated() {
+            let todo_dir = test_dir("storage-migrate");
+            let file = TodoState::Done.file_path(&todo_dir);
+            fs::write(
+                &file,
+                "bob\tlegacy thing\t01.05.2024\t12:00\t14:22\t24-04-20\n",
+            )
+            .unwrap();
+
+            let todos = LineStorage::new(&todo_dir, TabFormat)
+                .load(&TodoState::Done)
+                .unwrap();
+            let content = fs::read_to_string(&file).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(todos.len(), 1);
+            assert_eq!(todos[0].description, "legacy thing");
+            assert_eq!(todos[0].status, TodoState::Done);
+            assert!(content.starts_with(&TabFormat.header()));
+            assert_eq!(
+                TabFormat.decode(content.lines().nth(1).unwrap()),
+                Ok(todos[0].clone())
+            );
+        }
+
+        #[test]
+        fn test_version_2_file_is_upgraded() {
+            let todo_dir = test_dir("storage-upgrade");
+            let file = TodoState::Open.file_path(&todo_dir);
+            fs::write(
+                &file,
+                "# todo-cli format 2\nabc123\told +legacy\topen\t\t\t\t2024-04-20 09:30:12\tbob\tbob\t2024-04-20\n",
+            )
+            .unwrap();
+
+            let todos = LineStorage::new(&todo_dir, TabFormat)
+                .load(&TodoState::Open)
+                .unwrap();
+            let content = fs::read_to_string(&file).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(todos[0].id, "abc123");
+            assert_eq!(todos[0].source, None);
+            assert_eq!(todos[0].tags, vec!["+legacy"]);
+            assert_eq!(content.lines().next(), Some(TabFormat.header().as_str()));
+            assert_eq!(content.lines().nth(1).unwrap().split('\t').count(), 15);
+        }
+
+        #[test]
+        fn test_tab_file_is_converted_to_json_lines() {
+            let todo_dir = test_dir("storage-convert");
+            let todo = done_todo();
+            let tab = LineStorage::new(&todo_dir, TabFormat);
+            tab.create().unwrap();
+            tab.insert(&todo).unwrap();
+
+            let todos = LineStorage::new(&todo_dir, JsonLinesFormat)
+                .load(&TodoState::Done)
+                .unwrap();
+            let content = fs::read_to_string(TodoState::Done.file_path(&todo_dir)).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(todos, vec![todo]);
+            assert!(content.starts_with(&JsonLinesFormat.header()));
+        }
+
+        #[test]
+        fn test_sqlite_storage() {
+            let todo_dir = test_dir("storage-sqlite");
+            let storage = SqliteStorage::new(&todo_dir);
+            assert_eq!(storage.create(), Ok(vec![SQLITE_FILE_NAME.to_string()]));
+
+            let done = done_todo();
+            let mut open = fixed_todo("write tests");
+            open.id = "open01".to_string();
+            storage.insert(&done).unwrap();
+            storage.insert(&open).unwrap();
+            let loaded_open = storage.load(&TodoState::Open).unwrap();
+            let by_creator = storage
+                .query(&DatabaseField::CreatedBy("alice".to_string()))
+                .unwrap();
+
+            let mut finished = open.clone();
+            finished.status = TodoState::Done;
+            storage.update(&open, &finished).unwrap();
+            let loaded_done = storage.load(&TodoState::Done).unwrap();
+            storage.update_all(&[(Some(done.clone()), None)]).unwrap();
+            let left = storage.load(&TodoState::Done).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(loaded_open, vec![open]);
+            assert_eq!(by_creator.len(), 2);
+            assert_eq!(loaded_done, vec![done, finished.clone()]);
+            assert_eq!(left, vec![finished]);
+        }
+
+        #[test]
+        fn test_old_sqlite_database_is_migrated_once() {
+            let todo_dir = test_dir("storage-sqlite-migrate");
+            let storage = SqliteStorage::new(&todo_dir);
+            let old = Connection::open(&storage.db_path).unwrap();
+            old.execute_batch(
+                "CREATE TABLE todos (id TEXT PRIMARY KEY, description TEXT NOT NULL,
+                    status TEXT NOT NULL, due_date TEXT, due_time TEXT, done_date TEXT,
+                    create_date TEXT NOT NULL, created_by TEXT NOT NULL,
+                    last_changed_by TEXT NOT NULL, last_change_date TEXT NOT NULL);
+                INSERT INTO todos VALUES ('abc123', 'old +legacy', 'open', NULL, NULL, NULL,
+                    '2024-04-20 09:30:12', 'bob', 'bob', '2024-04-20');
+                PRAGMA user_version = 2;",
+            )
+            .unwrap();
+            drop(old);
+
+            let loaded = storage.load(&TodoState::Open).unwrap();
+            let connection = storage.connect().unwrap();
+            let version: u32 = connection
+                .query_row("PRAGMA user_version", [], |row| row.get(0))
+                .unwrap();
+            connection
+                .execute("UPDATE todos SET status = 'later'", [])
+                .unwrap();
+            let broken_status = storage.query(&DatabaseField::Id("abc123".to_string()));
+            connection
+                .execute(
+                    "UPDATE todos SET status = 'open', due_date = '20.04.2024'",
+                    [],
+                )
+                .unwrap();
+            let broken_date = storage.load(&TodoState::Open);
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert_eq!(loaded.len(), 1);
+            assert_eq!(loaded[0].tags, vec!["+legacy"]);
+            assert_eq!(version, SQLITE_SCHEMA_VERSION);
+            assert!(broken_status.is_err());
+            assert!(broken_date.is_err());
+        }
+
+        #[test]
+        fn test_convert_storage() {
+            let todo_dir = test_dir("storage-convert-all");
+            let tab = LineStorage::new(&todo_dir, TabFormat);
+            tab.create().unwrap();
+            let mut open = fixed_todo("write tests");
+            open.id = "open01".to_string();
+            tab.insert(&open).unwrap();
+            tab.insert(&done_todo()).unwrap();
+            let open_file = TodoState::Open.file_path(&todo_dir);
+            let open_before = fs::read_to_string(&open_file).unwrap();
+            let deleted_file = TodoState::Deleted.file_path(&todo_dir);
+            fs::write(&deleted_file, "# todo-cli format 99\n").unwrap();
+
+            let unreadable = convert_storage(&todo_dir, &StorageKind::Tab, &StorageKind::JsonLines);
+            let open_after_fail = fs::read_to_string(&open_file).unwrap();
+            tab.save(&TodoState::Deleted, &[]).unwrap();
+            let to_json = convert_storage(&todo_dir, &StorageKind::Tab, &StorageKind::JsonLines);
+            let json_content = fs::read_to_string(&open_file).unwrap();
+            let to_sqlite =
+                convert_storage(&todo_dir, &StorageKind::JsonLines, &StorageKind::Sqlite);
+            let in_sqlite = SqliteStorage::new(&todo_dir).load(&TodoState::Done);
+            let staging_left = todo_dir.join(STAGING_DIR_NAME).exists();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert!(unreadable.is_err());
+            assert_eq!(open_after_fail, open_before);
+            assert_eq!(to_json, Ok(2));
+            assert!(json_content.starts_with(&JsonLinesFormat.header()));
+            assert_eq!(to_sqlite, Ok(2));
+            assert_eq!(in_sqlite, Ok(vec![done_todo()]));
+            assert!(!staging_left);
+        }
+
+        /// A tab storage that can't write `.todo.todo`, like after a crash at the wrong time.
+        struct FailingOpenStorage(LineStorage<TabFormat>);
+        impl TodoStorage for FailingOpenStorage {
+            fn create(&self) -> Result<Vec<String>, String> {
+                self.0.create()
+            }
+            fn load(&self, status: &TodoState) -> Result<Vec<Todo>, String> {
+                self.0.load(status)
+            }
+            fn save(&self, status: &TodoState, todos: &[Todo]) -> Result<(), String> {
+                match status {
+                    TodoState::Open => Err("crashed".to_string()),
+                    _ => self.0.save(status, todos),
+                }
+            }
+            fn insert(&self, todo: &Todo) -> Result<(), String> {
+                self.0.insert(todo)
+            }
+            fn update(&self, old_todo: &Todo, new_todo: &Todo) -> Result<(), String> {
+                self.0.update(old_todo, new_todo)
+            }
+            fn files(&self) -> Vec<PathBuf> {
+                self.0.files()
+            }
+        }
+
+        #[test]
+        fn test_update_all_writes_moved_todos_first() {
+            let todo_dir = test_dir("storage-update-all-order");
+            let storage = FailingOpenStorage(LineStorage::new(&todo_dir, TabFormat));
+            storage.create().unwrap();
+            let open = fixed_todo("write tests");
+            storage.insert(&open).unwrap();
+            let mut done = open.clone();
+            done.status = TodoState::Done;
+
+            let failed = storage.update_all(&[(Some(open.clone()), Some(done.clone()))]);
+            let open_after = storage.load(&TodoState::Open).unwrap();
+            let done_after = storage.load(&TodoState::Done).unwrap();
+            fs::remove_dir_all(&todo_dir).unwrap();
+
+            assert!(failed.is_err());
+            assert_eq!(open_after, vec![open]);
+            assert_eq!(done_after, vec![done]);
+        }
+
+        #[test]
+        fn test_update_all_writes_nothing_if_one_is_missing() {
+            let todo_dir = test_dir("storage-update-all");
+            let storages: Vec<Box<dyn TodoStorage>> = vec![
+                Box::new(LineStorage::new(&todo_dir, TabFormat)),
+                Box::new(SqliteStorage::new(&todo_dir)),
+            ];
+            for storage in storages {
+                storage.create().unwrap();
+                let first = fixed_todo("write tests");
+                let mut second = first.clone();
+                second.id = "second".to_string();
+                let mut missing = first.clone();
+                missing.id = "missin".to_string();
+                storage.insert(&first).unwrap();
+                storage.insert(&second).unwrap();
+
+                let mut finished = first.clone();
+                finished.status = TodoState::Done;
+                let mut added = first.clone();
+                added.id = "added1".to_string();
+                let failed = storage.update_all(&[
+                    (Some(first.clone()), Some(finished.clone())),
+                    (Some(missing), None),
+                ]);
+                let failed_add = storage.update_all(&[
+                    (Some(first.clone()), Some(finished.clone())),
+                    (None, Some(second.clone())),
+                ]);
+                let open_after_fail = storage.load(&TodoState::Open).unwrap();
+                storage
+                    .update_all(&[
+                        (Some(first.clone()), Some(finished.clone())),
+                        (Some(second), None),
+                        (None, Some(added.clone())),
+                    ])
+                    .unwrap();
+
+                assert!(failed.is_err());
+                assert!(failed_add.is_err());
+                assert_eq!(open_after_fail.len(), 2);
+                assert_eq!(storage.load(&TodoState::Open).unwrap(), vec![added]);
+                assert_eq!(storage.load(&TodoState::Done).unwrap(), vec![finished]);
+            }
+            fs::remove_dir_all(&todo_dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,129 @@
+/// Todos and directories the tests of all modules share.
+pub mod test_helper {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        process::{self, Command},
+    };
+
+    use chrono::{Duration, Local, NaiveDate};
+
+    use crate::databasehandler::database_handler::todo_database::{Todo, TodoState};
+    use crate::storagehandler::storage_handler::get_storage;
+
+    /// An open todo of alice without due date, created now.
+    pub fn test_todo(description: &str) -> Todo {
+        Todo::new(
+            description.to_string(),
+            TodoState::Open,
+            None,
+            None,
+            Local::now().naive_local(),
+            "alice".to_string(),
+        )
+    }
+
+    /// `todo 0` to `todo {count - 1}`.
+    pub fn test_todos(count: usize) -> Vec<Todo> {
+        (0..count)
+            .map(|i| test_todo(&format!("todo {}", i)))
+            .collect()
+    }
+
+    /// A todo of alice due `days` from today, `None` is no due date.
+    pub fn due_todo(description: &str, days: Option<i64>) -> Todo {
+        let mut todo = test_todo(description);
+        todo.due_date = days.map(|days| Local::now().date_naive() + Duration::days(days));
+        todo
+    }
+
+    /// A todo whose fields don't depend on when the test runs, for the formats.
+    /// `abc123`, created by alice on 2024-04-20 14:22, due 2024-05-01, changed 2024-04-21.
+    pub fn fixed_todo(description: &str) -> Todo {
+        let mut todo = Todo::new(
+            description.to_string(),
+            TodoState::Open,
+            NaiveDate::from_ymd_opt(2024, 5, 1),
+            None,
+            NaiveDate::from_ymd_opt(2024, 4, 20)
+                .unwrap()
+                .and_hms_opt(14, 22, 0)
+                .unwrap(),
+            "alice".to_string(),
+        );
+        todo.id = "abc123".to_string();
+        todo.last_change_date = NaiveDate::from_ymd_opt(2024, 4, 21).unwrap();
+        todo
+    }
+
+    /// `fixed_todo` with the id `id`, for tests that need several of them.
+    pub fn fixed_todo_with_id(id: &str, description: &str) -> Todo {
+        let mut todo = fixed_todo(description);
+        todo.id = id.to_string();
+        todo
+    }
+
+    /// An empty directory for the test `name`, remove it at the end of the test.
+    pub fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-cli-test-{}-{}", name, process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A todo directory of carol with the tab storage, like `todo create` leaves it.
+    pub fn test_todo_dir(name: &str) -> PathBuf {
+        let todo_dir = test_dir(name);
+        create_todo_files(&todo_dir);
+        todo_dir
+    }
+
+    fn create_todo_files(todo_dir: &Path) {
+        fs::write(todo_dir.join(".todo.config"), "carol\nin_file\ntab\n").unwrap();
+        get_storage(todo_dir).create().unwrap();
+    }
+
+    /// Runs `git` in `dir`, `false` if it failed or isn't installed.
+    ///
+    /// Commits are made by alice on 2024-04-20 14:22 UTC, so the same commits get the same hash.
+    pub fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_INDEX_FILE")
+            .env("GIT_AUTHOR_DATE", "2024-04-20T14:22:00Z")
+            .env("GIT_COMMITTER_DATE", "2024-04-20T14:22:00Z")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=alice",
+                "-c",
+                "user.email=alice@example.com",
+            ])
+            .args(["-c", "commit.gpgSign=false"])
+            .args(args)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    /// `test_dir` as a new git repository on branch `main`. `None` if git isn't installed.
+    pub fn test_repo(name: &str) -> Option<PathBuf> {
+        let repo = test_dir(name);
+        if !git(&repo, &["init", "-q", "--object-format=sha1"]) {
+            fs::remove_dir_all(&repo).unwrap();
+            return None;
+        }
+        assert!(git(&repo, &["symbolic-ref", "HEAD", "refs/heads/main"]));
+        Some(repo)
+    }
+
+    /// `test_todo_dir` that is a git repository too, see `test_repo`.
+    pub fn test_todo_repo(name: &str) -> Option<PathBuf> {
+        let repo = test_repo(name)?;
+        create_todo_files(&repo);
+        Some(repo)
+    }
+}